@@ -0,0 +1,586 @@
+use core::fmt;
+use std::{
+    cmp::Ordering,
+    ops::{Add, Mul, Sub},
+    str::FromStr,
+};
+
+/// An unsigned integer as big as it needs to be. Stored as base 2^32 limbs,
+/// least significant first, with no zero limbs on the end. Zero is no limbs
+/// at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn zero() -> Self {
+        Self { limbs: vec![] }
+    }
+
+    pub fn one() -> Self {
+        Self::from(1u32)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn trim(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+
+        self
+    }
+
+    /// Divide in place by something that fits in a limb, returning the
+    /// remainder.
+    fn div_rem_limb(&mut self, divisor: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let current = (rem << 32) | *limb as u64;
+            *limb = (current / divisor as u64) as u32;
+            rem = current % divisor as u64;
+        }
+
+        *self = std::mem::take(self).trim();
+        rem as u32
+    }
+
+    /// Multiply by `mul` and add `add` in place, both small enough to fit
+    /// in a limb.
+    fn mul_add_limb(&mut self, mul: u32, add: u32) {
+        let mut carry = add as u64;
+        for limb in self.limbs.iter_mut() {
+            let current = *limb as u64 * mul as u64 + carry;
+            *limb = current as u32;
+            carry = current >> 32;
+        }
+
+        if carry != 0 {
+            self.limbs.push(carry as u32);
+        }
+
+        *self = std::mem::take(self).trim();
+    }
+
+    /// How many bits it takes to write this number down. Zero for zero.
+    pub fn bit_len(&self) -> usize {
+        match self.limbs.last() {
+            Some(top) => self.limbs.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    /// Bit `bit`, counting up from the least significant.
+    pub fn bit(&self, bit: usize) -> bool {
+        self.limbs
+            .get(bit / 32)
+            .map(|limb| limb & (1 << (bit % 32)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// Multiply by two, plus one if `low` is set.
+    fn shl_one(&mut self, low: bool) {
+        let mut carry = low as u32;
+        for limb in self.limbs.iter_mut() {
+            let next = *limb >> 31;
+            *limb = (*limb << 1) | carry;
+            carry = next;
+        }
+
+        if carry != 0 {
+            self.limbs.push(carry);
+        }
+    }
+
+    /// None if `rhs` is bigger than us.
+    pub fn checked_sub(&self, rhs: &BigUint) -> Option<BigUint> {
+        if self < rhs {
+            return None;
+        }
+
+        let mut limbs = Vec::with_capacity(self.limbs.len());
+        let mut borrow = 0i64;
+        for (idx, limb) in self.limbs.iter().enumerate() {
+            let mut current = *limb as i64 - *rhs.limbs.get(idx).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if current < 0 {
+                current += 1 << 32;
+                borrow = 1;
+            }
+            limbs.push(current as u32);
+        }
+
+        Some(BigUint { limbs }.trim())
+    }
+
+    pub fn pow(&self, mut exp: u32) -> BigUint {
+        let mut result = BigUint::one();
+        let mut base = self.clone();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = &result * &base;
+            }
+
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
+            }
+        }
+
+        result
+    }
+
+    /// The quotient and remainder. Long division one bit at a time, which is
+    /// slow, but our numbers aren't that big.
+    ///
+    /// # Panics
+    /// If `divisor` is zero.
+    pub fn div_rem(&self, divisor: &BigUint) -> (BigUint, BigUint) {
+        assert!(!divisor.is_zero(), "attempt to divide by zero");
+
+        if let [small] = divisor.limbs[..] {
+            let mut quotient = self.clone();
+            let rem = quotient.div_rem_limb(small);
+            return (quotient, BigUint::from(rem));
+        }
+
+        let mut quotient = BigUint {
+            limbs: vec![0; self.limbs.len()],
+        };
+        let mut rem = BigUint::zero();
+        for bit in (0..self.bit_len()).rev() {
+            rem.shl_one(self.bit(bit));
+
+            if rem >= *divisor {
+                rem = &rem - divisor;
+                quotient.limbs[bit / 32] |= 1 << (bit % 32);
+            }
+        }
+
+        (quotient.trim(), rem)
+    }
+
+    /// None if it doesn't fit.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.limbs.len() > 4 {
+            return None;
+        }
+
+        Some(
+            self.limbs
+                .iter()
+                .rev()
+                .fold(0u128, |acc, limb| (acc << 32) | *limb as u128),
+        )
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(mut value: u128) -> Self {
+        let mut limbs = vec![];
+        while value != 0 {
+            limbs.push(value as u32);
+            value >>= 32;
+        }
+
+        Self { limbs }
+    }
+}
+
+macro_rules! from_small {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for BigUint {
+                fn from(value: $t) -> Self {
+                    Self::from(value as u128)
+                }
+            }
+        )*
+    };
+}
+
+from_small!(u8, u16, u32, u64, usize);
+
+impl Add for &BigUint {
+    type Output = BigUint;
+
+    fn add(self, rhs: Self) -> BigUint {
+        let (long, short) = if self.limbs.len() >= rhs.limbs.len() {
+            (self, rhs)
+        } else {
+            (rhs, self)
+        };
+
+        let mut limbs = Vec::with_capacity(long.limbs.len() + 1);
+        let mut carry = 0u64;
+        for (idx, limb) in long.limbs.iter().enumerate() {
+            let sum = *limb as u64 + *short.limbs.get(idx).unwrap_or(&0) as u64 + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+
+        if carry != 0 {
+            limbs.push(carry as u32);
+        }
+
+        BigUint { limbs }
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, rhs: Self) -> BigUint {
+        &self + &rhs
+    }
+}
+
+impl Sub for &BigUint {
+    type Output = BigUint;
+
+    /// # Panics
+    /// If `rhs` is bigger than us, like the primitives do in debug.
+    fn sub(self, rhs: Self) -> BigUint {
+        self.checked_sub(rhs)
+            .expect("attempt to subtract with overflow")
+    }
+}
+
+impl Sub for BigUint {
+    type Output = BigUint;
+
+    fn sub(self, rhs: Self) -> BigUint {
+        &self - &rhs
+    }
+}
+
+impl Mul for &BigUint {
+    type Output = BigUint;
+
+    fn mul(self, rhs: Self) -> BigUint {
+        if self.is_zero() || rhs.is_zero() {
+            return BigUint::zero();
+        }
+
+        // Schoolbook. Every partial product plus what's already there plus
+        // the carry still fits in a u64.
+        let mut limbs = vec![0u32; self.limbs.len() + rhs.limbs.len()];
+        for (i, a) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in rhs.limbs.iter().enumerate() {
+                let current = *a as u64 * *b as u64 + limbs[i + j] as u64 + carry;
+                limbs[i + j] = current as u32;
+                carry = current >> 32;
+            }
+            limbs[i + rhs.limbs.len()] = carry as u32;
+        }
+
+        BigUint { limbs }.trim()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, rhs: Self) -> BigUint {
+        &self * &rhs
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // Peel off nine decimal digits at a time, they come out backwards
+        let mut chunks = vec![];
+        let mut rest = self.clone();
+        while !rest.is_zero() {
+            chunks.push(rest.div_rem_limb(1_000_000_000));
+        }
+
+        let mut chunks = chunks.into_iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // No zero limbs on the end so more limbs is always bigger
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("can't parse an empty string as a number".into());
+        }
+
+        let mut num = BigUint::zero();
+        for c in s.chars() {
+            match c.to_digit(10) {
+                Some(digit) => num.mul_add_limb(10, digit),
+                None => return Err(format!("'{}' is not a digit in '{}'", c, s)),
+            }
+        }
+
+        Ok(num)
+    }
+}
+
+/// A signed [BigUint]. Zero is never negative.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: BigUint,
+}
+
+impl BigInt {
+    fn new(negative: bool, magnitude: BigUint) -> Self {
+        Self {
+            negative: negative && !magnitude.is_zero(),
+            magnitude,
+        }
+    }
+
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn magnitude(&self) -> &BigUint {
+        &self.magnitude
+    }
+
+    pub fn pow(&self, exp: u32) -> BigInt {
+        Self::new(self.negative && exp % 2 == 1, self.magnitude.pow(exp))
+    }
+
+    /// Rounds towards zero like the primitives do, so the remainder has the
+    /// same sign as `self`.
+    ///
+    /// # Panics
+    /// If `divisor` is zero.
+    pub fn div_rem(&self, divisor: &BigInt) -> (BigInt, BigInt) {
+        let (quotient, rem) = self.magnitude.div_rem(&divisor.magnitude);
+
+        (
+            Self::new(self.negative != divisor.negative, quotient),
+            Self::new(self.negative, rem),
+        )
+    }
+
+    /// None if it doesn't fit.
+    pub fn to_i128(&self) -> Option<i128> {
+        let magnitude = self.magnitude.to_u128()?;
+        if self.negative {
+            0i128.checked_sub_unsigned(magnitude)
+        } else {
+            i128::try_from(magnitude).ok()
+        }
+    }
+}
+
+impl From<BigUint> for BigInt {
+    fn from(magnitude: BigUint) -> Self {
+        Self::new(false, magnitude)
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(value: i128) -> Self {
+        Self::new(value < 0, BigUint::from(value.unsigned_abs()))
+    }
+}
+
+macro_rules! from_small_signed {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for BigInt {
+                fn from(value: $t) -> Self {
+                    Self::from(value as i128)
+                }
+            }
+        )*
+    };
+}
+
+from_small_signed!(i8, i16, i32, i64, isize);
+
+impl Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> BigInt {
+        if self.negative == rhs.negative {
+            return BigInt::new(self.negative, &self.magnitude + &rhs.magnitude);
+        }
+
+        // Different signs, so the bigger magnitude wins and keeps its sign
+        match self.magnitude.cmp(&rhs.magnitude) {
+            Ordering::Less => BigInt::new(rhs.negative, &rhs.magnitude - &self.magnitude),
+            _ => BigInt::new(self.negative, &self.magnitude - &rhs.magnitude),
+        }
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: Self) -> BigInt {
+        &self + &rhs
+    }
+}
+
+impl Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> BigInt {
+        self + &BigInt::new(!rhs.negative, rhs.magnitude.clone())
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: Self) -> BigInt {
+        &self - &rhs
+    }
+}
+
+impl Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> BigInt {
+        BigInt::new(
+            self.negative != rhs.negative,
+            &self.magnitude * &rhs.magnitude,
+        )
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: Self) -> BigInt {
+        &self * &rhs
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, false) => self.magnitude.cmp(&other.magnitude),
+            (true, true) => other.magnitude.cmp(&self.magnitude),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+
+        write!(f, "{}", self.magnitude)
+    }
+}
+
+impl FromStr for BigInt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('-') {
+            Some(rest) => Ok(Self::new(true, rest.parse()?)),
+            None => Ok(Self::new(false, s.strip_prefix('+').unwrap_or(s).parse()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rng::Rng;
+
+    use super::*;
+
+    #[test]
+    fn big_arithmetic_disagrees_with_i128() {
+        let mut rng = Rng::new(32);
+        let mut random = || {
+            let magnitude = (rng.next_u64() >> rng.below(64)) as i128;
+            if rng.below(2) == 0 {
+                magnitude
+            } else {
+                -magnitude
+            }
+        };
+
+        for _ in 0..2000 {
+            let (a, b) = (random(), random());
+            let (big_a, big_b) = (BigInt::from(a), BigInt::from(b));
+
+            assert_eq!((&big_a + &big_b).to_i128(), Some(a + b));
+            assert_eq!((&big_a - &big_b).to_i128(), Some(a - b));
+            assert_eq!((&big_a * &big_b).to_i128(), Some(a * b));
+            assert_eq!(big_a.cmp(&big_b), a.cmp(&b));
+            assert_eq!(big_a.to_string(), a.to_string());
+            assert_eq!(a.to_string().parse::<BigInt>(), Ok(big_a.clone()));
+
+            if b != 0 {
+                let (quotient, rem) = big_a.div_rem(&big_b);
+                assert_eq!(
+                    (quotient.to_i128(), rem.to_i128()),
+                    (Some(a / b), Some(a % b))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn big_pow_and_div_rem_are_wrong() {
+        let three = BigUint::from(3u8);
+        let big = three.pow(200);
+        let (quotient, rem) = big.div_rem(&three.pow(150));
+
+        assert_eq!(quotient, three.pow(50));
+        assert!(rem.is_zero());
+
+        let plus_one = &big + &BigUint::one();
+        let (quotient, rem) = plus_one.div_rem(&three.pow(100));
+        assert_eq!(quotient, three.pow(100));
+        assert_eq!(rem, BigUint::one());
+        assert_eq!(big.to_string().parse::<BigUint>(), Ok(big));
+    }
+}
@@ -1,7 +1,33 @@
-use aoc2021::day_parse_lines;
+use aoc2021::{
+    arg_value, day_parse_lines, error, info, sonar,
+    svg::{self, Element, Scale, Svg},
+    window::SlidingExt,
+    AdventError,
+};
 
 fn main() {
     let values: Vec<usize> = day_parse_lines!();
+    let window = match window_arg() {
+        Ok(window) => window,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(path) = arg_value::<String>("--svg").unwrap() {
+        depth_svg(&values, window.unwrap_or(3)).save(&path).unwrap();
+        info!("Wrote the depth profile to {}", path);
+    }
+
+    if let Some(window) = window {
+        println!(
+            "Depth increased {} times with a window of {}",
+            sonar::offset_increases(&values, window),
+            window
+        );
+        return;
+    }
 
     let p1 = part1(&values);
     let p2 = part2(&values);
@@ -12,70 +38,106 @@ fn main() {
     )
 }
 
-pub fn part1(values: &[usize]) -> usize {
-    let mut previous = *values.first().unwrap();
-    let mut increase = 0;
-    for value in values.iter().skip(1) {
-        if *value > previous {
-            increase += 1
-        }
-
-        previous = *value;
+/// `--window`, which has to be at least one reading.
+fn window_arg() -> Result<Option<usize>, AdventError> {
+    match arg_value("--window")? {
+        Some(0) => Err(AdventError::Argument {
+            flag: "--window".to_owned(),
+            inner: "a window has to be at least one reading".into(),
+        }),
+        window => Ok(window),
     }
-
-    increase
 }
 
-pub fn part2(values: &[usize]) -> usize {
-    let mut slidesum = SlidingSum::new(values.iter().take(3).map(|v| *v).collect());
-    let mut slide_previous = slidesum.sum();
-    let mut slide_increased = 0;
-
-    for value in values.iter().skip(3) {
-        slidesum.push(*value);
-        let sum = slidesum.sum();
-
-        if sum > slide_previous {
-            slide_increased += 1;
-        }
-
-        slide_previous = sum;
-    }
-
-    slide_increased
+/// How many readings are deeper than the one before. Nothing's deeper with
+/// fewer than two.
+pub fn part1(values: &[usize]) -> usize {
+    values.windows(2).filter(|pair| pair[1] > pair[0]).count()
 }
 
-// Tried to make this generic but it got too weird.
-pub struct SlidingSum {
-    values: Vec<usize>,
-    idx: usize,
+pub fn part2(values: &[usize]) -> usize {
+    let sums: Vec<usize> = values
+        .iter()
+        .copied()
+        .sliding::<3>()
+        .map(|w| w.sum)
+        .collect();
+
+    part1(&sums)
 }
 
-impl SlidingSum {
-    pub fn new(initial: Vec<usize>) -> Self {
-        Self {
-            values: initial,
-            idx: 0,
+/// The depths going down the page, with a dot on every one deeper than the
+/// one before, and the sum of every `window` depths on top. The sums are
+/// about `window` times deeper, so they get their own scale, numbered down
+/// the right.
+///
+/// # Panics
+/// If `window` is 0.
+pub fn depth_svg(values: &[usize], window: usize) -> Svg {
+    let (width, height) = (800.0, 400.0);
+    let last = values.len().saturating_sub(1) as f64;
+    let x = Scale::new((0.0, last), (60.0, width - 60.0));
+    let y = Scale::fit(values.iter().map(|v| *v as f64), (40.0, height - 50.0));
+    let point = |reading: f64, depth: f64| (x.apply(reading), y.apply(depth));
+
+    let mut svg = Svg::new(width, height);
+    svg.push(svg::axes(&x, &y, "reading", "depth"));
+
+    let depths: Vec<_> = values
+        .iter()
+        .enumerate()
+        .map(|(idx, v)| point(idx as f64, *v as f64))
+        .collect();
+    svg.push(Element::polyline(&depths).attr("stroke", "steelblue"));
+
+    let mut increases = Element::new("g").attr("fill", "crimson");
+    for idx in 1..values.len() {
+        if values[idx] > values[idx - 1] {
+            increases = increases.child(Element::circle(depths[idx], 2.0));
         }
     }
-
-    pub fn push(&mut self, value: usize) {
-        self.values[self.idx] = value;
-        self.idx += 1;
-
-        if self.idx == self.values.len() {
-            self.idx = 0;
+    svg.push(increases);
+
+    // Each sum goes in the middle of its window
+    let middle = (window - 1) as f64 / 2.0;
+    let sums: Vec<usize> = values.windows(window).map(|w| w.iter().sum()).collect();
+    let sum_y = Scale::fit(sums.iter().map(|s| *s as f64), y.range);
+    let sum_points: Vec<_> = sums
+        .iter()
+        .enumerate()
+        .map(|(idx, sum)| (x.apply(idx as f64 + middle), sum_y.apply(*sum as f64)))
+        .collect();
+    svg.push(Element::polyline(&sum_points).attr("stroke", "orange"));
+
+    let right = x.range.1;
+    let mut sum_axis = Element::new("g")
+        .child(Element::line((right, y.range.0), (right, y.range.1)).attr("stroke", "orange"));
+    if !sums.is_empty() {
+        for end in [sum_y.domain.0, sum_y.domain.1] {
+            sum_axis = sum_axis.child(
+                Element::label((right + 4.0, sum_y.apply(end) + 4.0), svg::number(end))
+                    .attr("fill", "orange"),
+            );
         }
     }
-
-    pub fn sum(&self) -> usize {
-        self.values.iter().sum()
-    }
+    svg.push(sum_axis);
+
+    let legend = Element::new("g")
+        .attr("font-size", 11)
+        .child(Element::label((width - 200.0, 16.0), "depth").attr("fill", "steelblue"))
+        .child(Element::label((width - 155.0, 16.0), "deeper").attr("fill", "crimson"))
+        .child(
+            Element::label((width - 105.0, 16.0), format!("sum of {}", window))
+                .attr("fill", "orange"),
+        );
+    svg.push(legend);
+
+    svg
 }
 
 #[cfg(test)]
 mod test {
-    use aoc2021::day_parse_lines;
+    use aoc2021::{day_parse_lines, rng::Rng, test_support::assert_snapshot};
 
     use super::*;
 
@@ -92,4 +154,53 @@ mod test {
 
         assert_eq!(part2(&values), 1608)
     }
+
+    #[test]
+    fn short_reports_have_wrong_increases() {
+        assert_eq!((part1(&[]), part2(&[])), (0, 0));
+        assert_eq!((part1(&[5]), part2(&[5])), (0, 0));
+        assert_eq!((part1(&[5, 6]), part2(&[5, 6])), (1, 0));
+        assert_eq!(part2(&[5, 6, 7]), 0);
+        assert_eq!(part2(&[5, 6, 7, 8]), 1);
+    }
+
+    #[test]
+    fn sonar_windows_disagree_with_parts() {
+        let values: Vec<usize> = day_parse_lines!();
+
+        assert_eq!(sonar::summed_increases(&values, 1), 1557);
+        assert_eq!(sonar::offset_increases(&values, 1), 1557);
+        assert_eq!(sonar::summed_increases(&values, 3), 1608);
+        assert_eq!(sonar::offset_increases(&values, 3), 1608);
+    }
+
+    #[test]
+    fn offset_increases_disagree_with_summed() {
+        let mut rng = Rng::new(1);
+
+        for _ in 0..50 {
+            let len = rng.below(300) as usize;
+            let depths: Vec<usize> = (0..len).map(|_| rng.below(10_000) as usize).collect();
+
+            for window in 0..=len + 1 {
+                assert_eq!(
+                    sonar::offset_increases(&depths, window),
+                    sonar::summed_increases(&depths, window),
+                    "window {} over {:?}",
+                    window,
+                    depths
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn example_depth_svg_has_changed() {
+        let values = [199, 200, 208, 210, 200, 207, 240, 269, 260, 263];
+
+        assert_snapshot(
+            "snapshots/day1_example.svg",
+            &depth_svg(&values, 3).to_string(),
+        );
+    }
 }
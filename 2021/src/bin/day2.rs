@@ -1,7 +1,19 @@
-use aoc2021::{day_parse_lines, MovementCommand, MovementDirection};
+use aoc2021::{
+    arg_flag, arg_value,
+    bigint::BigInt,
+    day_parse_lines, info,
+    svg::{self, Element, Scale, Svg},
+    MovementCommand, MovementDirection,
+};
 
 fn main() {
     let cmds: Vec<MovementCommand> = day_parse_lines!();
+    let exact = arg_flag("--exact");
+
+    if let Some(path) = arg_value::<String>("--svg").unwrap() {
+        course_svg(&cmds).save(&path).unwrap();
+        info!("Wrote both courses to {}", path);
+    }
 
     let (horizontal, depth) = part1(&cmds);
 
@@ -9,38 +21,108 @@ fn main() {
         "Part One:\nDepth is {} and horizontal position {}. The product of those is {}",
         horizontal,
         depth,
-        horizontal * depth
+        product(horizontal, depth, exact)
     );
 
     let (horizontal, depth, aim) = part2(&cmds);
 
-    println!("Part Two:\nDepth is {}, horizontal {}, and aim {}. The product of the horizontal and depth is {}", depth, horizontal, aim, depth * horizontal)
+    println!("Part Two:\nDepth is {}, horizontal {}, and aim {}. The product of the horizontal and depth is {}", depth, horizontal, aim, product(depth, horizontal, exact))
+}
+
+/// With `exact` we multiply as [BigInt]s so a long course can't overflow.
+fn product(a: isize, b: isize, exact: bool) -> String {
+    if exact {
+        (BigInt::from(a) * BigInt::from(b)).to_string()
+    } else {
+        (a * b).to_string()
+    }
 }
 
 fn part1(cmds: &[MovementCommand]) -> (isize, isize) {
-    cmds.iter()
-        .fold((0, 0), |(horizontal, depth), command| match command.dir {
-            MovementDirection::Forward => (horizontal + command.units, depth),
-            MovementDirection::Up => (horizontal, depth - command.units),
-            MovementDirection::Down => (horizontal, depth + command.units),
-        })
+    cmds.iter().fold((0, 0), move1)
 }
 
 fn part2(cmds: &[MovementCommand]) -> (isize, isize, isize) {
-    cmds.iter().fold(
-        (0, 0, 0),
-        |(horizontal, depth, aim), command| match command.dir {
-            MovementDirection::Forward => {
-                (horizontal + command.units, command.units * aim + depth, aim)
-            }
-            MovementDirection::Up => (horizontal, depth, aim - command.units),
-            MovementDirection::Down => (horizontal, depth, aim + command.units),
-        },
-    )
+    cmds.iter().fold((0, 0, 0), move2)
+}
+
+/// Part one's (horizontal, depth) after `command`
+fn move1((horizontal, depth): (isize, isize), command: &MovementCommand) -> (isize, isize) {
+    match command.dir {
+        MovementDirection::Forward => (horizontal + command.units, depth),
+        MovementDirection::Up => (horizontal, depth - command.units),
+        MovementDirection::Down => (horizontal, depth + command.units),
+    }
+}
+
+/// Part two's (horizontal, depth, aim) after `command`
+fn move2(
+    (horizontal, depth, aim): (isize, isize, isize),
+    command: &MovementCommand,
+) -> (isize, isize, isize) {
+    match command.dir {
+        MovementDirection::Forward => {
+            (horizontal + command.units, command.units * aim + depth, aim)
+        }
+        MovementDirection::Up => (horizontal, depth, aim - command.units),
+        MovementDirection::Down => (horizontal, depth, aim + command.units),
+    }
+}
+
+/// Every (horizontal, depth) the sub is at, starting from the surface
+type Course = Vec<(isize, isize)>;
+
+/// The course following part one's rules and the one following part two's.
+fn courses(cmds: &[MovementCommand]) -> (Course, Course) {
+    let mut one = vec![(0, 0)];
+    let mut two = vec![(0, 0)];
+    let (mut at1, mut at2) = ((0, 0), (0, 0, 0));
+
+    for command in cmds {
+        at1 = move1(at1, command);
+        at2 = move2(at2, command);
+        one.push(at1);
+        two.push((at2.0, at2.1));
+    }
+
+    (one, two)
+}
+
+/// Both courses as horizontal position against depth, going down the page,
+/// part one above part two. They get a plot each because part two goes
+/// about a thousand times deeper.
+fn course_svg(cmds: &[MovementCommand]) -> Svg {
+    let (width, panel) = (800.0, 300.0);
+    let (one, two) = courses(cmds);
+    let mut svg = Svg::new(width, panel * 2.0);
+
+    for (idx, (course, title)) in [(one, "part 1"), (two, "part 2")].iter().enumerate() {
+        let x = Scale::fit(course.iter().map(|p| p.0 as f64), (80.0, width - 20.0));
+        let y = Scale::fit(course.iter().map(|p| p.1 as f64), (40.0, panel - 50.0));
+        let points: Vec<_> = course
+            .iter()
+            .map(|(h, d)| (x.apply(*h as f64), y.apply(*d as f64)))
+            .collect();
+
+        svg.push(
+            Element::new("g")
+                .attr(
+                    "transform",
+                    format!("translate(0,{})", svg::number(panel * idx as f64)),
+                )
+                .child(svg::axes(&x, &y, "horizontal position", "depth"))
+                .child(Element::polyline(&points).attr("stroke", "steelblue"))
+                .child(Element::label((width - 20.0, 20.0), title).attr("text-anchor", "end")),
+        );
+    }
+
+    svg
 }
 
 #[cfg(test)]
 mod test {
+    use aoc2021::test_support::assert_snapshot;
+
     use super::*;
 
     #[test]
@@ -58,4 +140,34 @@ mod test {
 
         assert_eq!((1970, 1000556, 916), tup)
     }
+
+    #[test]
+    fn exact_product_is_wrong() {
+        assert_eq!(product(1970, 1000556, true), product(1970, 1000556, false));
+        assert_eq!(
+            product(isize::MAX, -3, true),
+            (isize::MAX as i128 * -3).to_string()
+        );
+    }
+
+    #[test]
+    fn example_course_svg_has_changed() {
+        let cmds: Vec<MovementCommand> = [
+            "forward 5",
+            "down 5",
+            "forward 8",
+            "up 3",
+            "down 8",
+            "forward 2",
+        ]
+        .iter()
+        .map(|c| c.parse().unwrap())
+        .collect();
+
+        let (one, two) = courses(&cmds);
+        assert_eq!(*one.last().unwrap(), part1(&cmds));
+        assert_eq!(*two.last().unwrap(), (15, 60));
+
+        assert_snapshot("snapshots/day2_example.svg", &course_svg(&cmds).to_string());
+    }
 }
@@ -1,134 +1,647 @@
 use core::str::FromStr;
 use std::cmp::Ordering;
 
-use aoc2021::day_parse;
+use aoc2021::{
+    arg_value,
+    bits::{words_for, Bits},
+    day_parse, debug, error, event,
+    events::{self, Event, Value},
+};
 
 fn main() {
+    let _events = events::FlushGuard;
     let dr: DiagnosticReport = day_parse!();
     let (g, e) = dr.gamma_epsilon();
 
     println!("Gamma {}, Epsilon {}. Product {}", g, e, g * e);
 
-    let (o2r, co2r) = dr.o2_co2();
-    let o2 = msb_into_dec(&o2r);
-    let co2 = msb_into_dec(&co2r);
-
-    println!("o2: {}, co2: {}. Product {}", o2, co2, o2 * co2)
-}
-
-fn lsb_into_dec(bin: &[u8]) -> usize {
-    let mut num = 0;
-    for idx in 0..bin.len() {
-        num += 2usize.pow(idx as u32) * bin[idx] as usize;
-    }
-
-    num
+    let method: RatingMethod = arg_value("--method").unwrap().unwrap_or_default();
+    let o2_criteria = arg_value("--o2")
+        .unwrap()
+        .unwrap_or(BitCriteria::MOST_COMMON);
+    let co2_criteria = arg_value("--co2")
+        .unwrap()
+        .unwrap_or(BitCriteria::LEAST_COMMON);
+
+    let (o2r, co2r) = match dr.life_support(method, o2_criteria, co2_criteria) {
+        Ok(ratings) => ratings,
+        Err(e) => {
+            error!("Couldn't find the life support ratings: {}", e);
+            events::flush();
+            std::process::exit(1);
+        }
+    };
+    let o2 = o2r.value.to_usize().expect("o2 rating too wide for usize");
+    let co2 = co2r
+        .value
+        .to_usize()
+        .expect("co2 rating too wide for usize");
+
+    println!("o2: {}, co2: {}. Product {}", o2, co2, o2 * co2);
+    println!(
+        "o2 resolved at position {:?}, co2 at {:?}",
+        o2r.resolved_at, co2r.resolved_at
+    )
 }
 
-fn msb_into_dec(bin: &[u8]) -> usize {
-    let bin: Vec<u8> = bin.iter().rev().map(|v| *v).collect();
-    lsb_into_dec(&bin)
+/// Positions count from the left, the way the puzzle writes the numbers, but
+/// bits are packed least significant first. This turns the former into the
+/// word and mask for the latter.
+fn locate(width: usize, position: usize) -> (usize, u64) {
+    let bit = width - 1 - position;
+    (bit / 64, 1 << (bit % 64))
 }
 
 #[derive(Debug, Clone)]
 struct DiagnosticReport {
-    /// Number stored as Least Significant Bit first! Challenge speaks in
-    /// Most Significant first.
-    bin: Vec<Vec<u8>>,
+    /// Every reading packed into `stride` words, one reading after the other.
+    /// See [Bits] for the order of the bits inside those words.
+    words: Vec<u64>,
+    stride: usize,
     bin_len: usize,
 }
 
 impl DiagnosticReport {
+    fn empty(bin_len: usize) -> Self {
+        Self {
+            words: vec![],
+            stride: words_for(bin_len),
+            bin_len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len() / self.stride
+    }
+
+    pub fn reading(&self, idx: usize) -> &[u64] {
+        &self.words[idx * self.stride..(idx + 1) * self.stride]
+    }
+
+    pub fn bits(&self, idx: usize) -> Bits {
+        Bits::from_words(self.bin_len, self.reading(idx).to_vec())
+    }
+
+    fn readings(&self) -> impl Iterator<Item = &[u64]> {
+        self.words.chunks_exact(self.stride)
+    }
+
     pub fn zeros_ones(&self, position: usize) -> (usize, usize) {
-        let mut ones = 0;
-        let mut zeros = 0;
+        let (word, mask) = locate(self.bin_len, position);
+        let ones = self
+            .readings()
+            .filter(|reading| reading[word] & mask != 0)
+            .count();
+
+        (self.len() - ones, ones)
+    }
 
-        for value in &self.bin {
-            match value[position] {
-                0 => zeros += 1,
-                1 => ones += 1,
-                _ => unreachable!(),
+    /// How many ones are in every position, indexed by position. One pass
+    /// over the readings that only visits the bits that are set.
+    pub fn column_ones(&self) -> Vec<usize> {
+        let mut ones = vec![0; self.bin_len];
+
+        for reading in self.readings() {
+            for (idx, word) in reading.iter().enumerate() {
+                let mut word = *word;
+                while word != 0 {
+                    let bit = idx * 64 + word.trailing_zeros() as usize;
+                    ones[self.bin_len - 1 - bit] += 1;
+                    word &= word - 1;
+                }
             }
         }
 
-        (zeros, ones)
+        ones
     }
 
-    pub fn common(&self, position: usize) -> (u8, u8) {
+    /// Which bit `criteria` picks for `position` across every reading.
+    pub fn select(&self, position: usize, criteria: BitCriteria) -> Result<Selection, String> {
         let (zeros, ones) = self.zeros_ones(position);
+        criteria
+            .select(zeros, ones)
+            .ok_or_else(|| Self::tied(position))
+    }
 
-        match zeros.cmp(&ones) {
-            Ordering::Greater => (0, 1),
-            Ordering::Less => (1, 0),
-            Ordering::Equal => (1, 0),
-        }
+    fn tied(position: usize) -> String {
+        format!("zeros and ones tied at position {}", position)
     }
 
+    /// Gamma and epsilon are the bits the o2 and co2 criteria would pick if
+    /// nothing were ever filtered out.
+    pub fn gamma_epsilon_bits(&self) -> (Bits, Bits) {
+        // Neither criteria errors on a tie so these can't fail
+        let picked = |criteria| {
+            self.clone()
+                .filter_until_one(criteria, Narrow::Never)
+                .unwrap()
+                .picked
+        };
+
+        (
+            picked(BitCriteria::MOST_COMMON),
+            picked(BitCriteria::LEAST_COMMON),
+        )
+    }
+
+    /// # Panics
+    /// If the report is wider than a usize.
     pub fn gamma_epsilon(&self) -> (usize, usize) {
-        let mut gamma = 0;
-        let mut epsilon = 0;
+        let (gamma, epsilon) = self.gamma_epsilon_bits();
+
+        (
+            gamma.to_usize().expect("gamma too wide for usize"),
+            epsilon.to_usize().expect("epsilon too wide for usize"),
+        )
+    }
+
+    /// Keep only the readings with `bit` at `position`. If none of them
+    /// have it there's nothing to filter on, so they all stay. That's where
+    /// this parts ways with [DiagnosticReport::o2_co2], which would keep
+    /// none.
+    fn keep(self, position: usize, bit: u8) -> Self {
+        let mut kept = Self::empty(self.bin_len);
+        let (word, mask) = locate(self.bin_len, position);
+        for reading in self.readings() {
+            if (reading[word] & mask != 0) == (bit == 1) {
+                kept.words.extend_from_slice(reading);
+            }
+        }
 
-        for index in 0..self.bin_len {
-            let index = self.bin_len - 1 - index;
-            let (common, uncommon) = self.common(index);
-            let position = 2usize.pow(index as u32);
+        debug!(
+            "position {} keeps {} of {} readings with a {}",
+            position,
+            kept.len(),
+            self.len(),
+            bit
+        );
+        event!(ReportEvent::Filtered {
+            position,
+            bit,
+            kept: kept.len(),
+            from: self.len(),
+        });
+
+        if kept.len() == 0 {
+            self
+        } else {
+            kept
+        }
+    }
+
+    /// Pick a bit at every position with `criteria`, left to right, keeping
+    /// only the readings with it until there's one left. With
+    /// [Narrow::Never] nobody's dropped, so every position is picked across
+    /// every reading. A tie that keeps both bits can't be picked, so it's an
+    /// error then.
+    pub fn filter_until_one(
+        mut self,
+        criteria: BitCriteria,
+        narrow: Narrow,
+    ) -> Result<Filtered, String> {
+        let mut picked = Bits::zero(self.bin_len);
+        let mut resolved_at = None;
+        // Nobody's dropped, so every column can be counted in one pass
+        let columns = match narrow {
+            Narrow::Never => Some(self.column_ones()),
+            Narrow::UntilOne => None,
+        };
+
+        for position in 0..self.bin_len {
+            if narrow == Narrow::UntilOne && self.len() <= 1 {
+                break;
+            }
+
+            let selection = match &columns {
+                Some(ones) => criteria
+                    .select(self.len() - ones[position], ones[position])
+                    .ok_or_else(|| Self::tied(position))?,
+                None => self.select(position, criteria)?,
+            };
+            let bit = match (selection, narrow) {
+                (Selection::Bit(bit), _) => bit,
+                (Selection::Both, Narrow::UntilOne) => continue,
+                (Selection::Both, Narrow::Never) => {
+                    return Err(format!(
+                        "can't keep both bits in a column, tie at position {}",
+                        position
+                    ))
+                }
+            };
+            picked.set(self.bin_len - 1 - position, bit == 1);
 
-            gamma += common as usize * position;
-            epsilon += uncommon as usize * position;
+            if narrow == Narrow::UntilOne {
+                self = self.keep(position, bit);
+                if self.len() == 1 {
+                    resolved_at = Some(position);
+                }
+            }
         }
 
-        (gamma, epsilon)
+        Ok(Filtered {
+            picked,
+            left: self,
+            resolved_at,
+        })
     }
 
+    /// Filter by `criteria` until there's one reading left. If we run out
+    /// of positions first the readings that are left have to all be the
+    /// same.
+    pub fn rating(self, criteria: BitCriteria) -> Result<Rating, String> {
+        let Filtered {
+            left, resolved_at, ..
+        } = self.filter_until_one(criteria, Narrow::UntilOne)?;
+
+        if left.readings().any(|reading| reading != left.reading(0)) {
+            return Err(Self::unresolved(left.len()));
+        }
+
+        Ok(Rating {
+            value: left.bits(0),
+            resolved_at,
+        })
+    }
+
+    fn unresolved(left: usize) -> String {
+        format!("{} different readings left after every position", left)
+    }
+
+    /// The most and least common bits at `position`, ones winning a tie.
+    pub fn common(&self, position: usize) -> (u8, u8) {
+        match self.select(position, BitCriteria::MOST_COMMON) {
+            Ok(Selection::Bit(common)) => (common, 1 - common),
+            _ => unreachable!("most common with ties to one always picks a bit"),
+        }
+    }
+
+    /// Split the readings into the ones with the most common bit at
+    /// `position` and the rest.
     pub fn filter_common_uncommon(self, position: usize) -> (DiagnosticReport, DiagnosticReport) {
-        let mut commons = vec![];
-        let mut uncommons = vec![];
-
-        let (common, uncommon) = self.common(position);
-        for v in self.bin {
-            if v[position] == common {
-                commons.push(v);
-            } else if v[position] == uncommon {
-                uncommons.push(v);
+        let mut commons = Self::empty(self.bin_len);
+        let mut uncommons = Self::empty(self.bin_len);
+
+        let (common, _) = self.common(position);
+        let (word, mask) = locate(self.bin_len, position);
+        for reading in self.readings() {
+            if (reading[word] & mask != 0) == (common == 1) {
+                commons.words.extend_from_slice(reading);
             } else {
-                panic!("WHY")
+                uncommons.words.extend_from_slice(reading);
             }
         }
 
-        (
-            Self {
-                bin: commons,
-                bin_len: self.bin_len,
-            },
-            Self {
-                bin: uncommons,
-                bin_len: self.bin_len,
-            },
-        )
+        event!(ReportEvent::Split {
+            position,
+            common,
+            commons: commons.len(),
+            uncommons: uncommons.len(),
+        });
+
+        (commons, uncommons)
     }
 
-    pub fn o2_co2(self) -> (Vec<u8>, Vec<u8>) {
-        let (mut dr_o2, mut dr_co2) = self.filter_common_uncommon(0);
-        let (mut o2, mut co2) = (None, None);
-        for idx in 1..dr_o2.bin_len {
-            if o2.is_none() {
-                dr_o2 = dr_o2.filter_common_uncommon(idx).0;
+    /// The original o2 and co2 ratings, kept to check the others against.
+    /// It only knows the puzzle's criteria, and when every co2 candidate has
+    /// the same bit it keeps the uncommons, which is nobody. That's None
+    /// here. [DiagnosticReport::rating] keeps everyone instead.
+    pub fn o2_co2(self) -> Option<(Rating, Rating)> {
+        let (mut dr_o2, mut dr_co2) = (self.clone(), self);
+        let (mut o2_at, mut co2_at) = (None, None);
 
-                if dr_o2.bin.len() == 1 {
-                    o2 = dr_o2.bin.get(0).map(|v| v.to_owned());
+        for idx in 0..dr_o2.bin_len {
+            if dr_o2.len() > 1 {
+                dr_o2 = dr_o2.filter_common_uncommon(idx).0;
+                if dr_o2.len() == 1 {
+                    o2_at = Some(idx);
                 }
             }
 
-            if co2.is_none() {
+            if dr_co2.len() > 1 {
                 dr_co2 = dr_co2.filter_common_uncommon(idx).1;
+                match dr_co2.len() {
+                    0 => return None,
+                    1 => co2_at = Some(idx),
+                    _ => (),
+                }
+            }
+        }
+
+        let rating = |dr: &DiagnosticReport, resolved_at| Rating {
+            value: dr.bits(0),
+            resolved_at,
+        };
+        Some((rating(&dr_o2, o2_at), rating(&dr_co2, co2_at)))
+    }
+
+    /// The same ratings as [DiagnosticReport::rating] without copying any
+    /// readings. Sorting puts every reading sharing a prefix next to each
+    /// other, zeros before ones in the position after it, so the candidates
+    /// are always ranges of `order` and each filter is a binary search for
+    /// where the zeros stop. It's only ever one range unless a tie keeps both
+    /// bits, which splits every range in two.
+    pub fn rating_partitioned(
+        &self,
+        order: &[usize],
+        criteria: BitCriteria,
+    ) -> Result<Rating, String> {
+        let mut ranges = vec![(0, order.len())];
+        let mut resolved_at = None;
+
+        for position in 0..self.bin_len {
+            if ranges.iter().map(|(lo, hi)| hi - lo).sum::<usize>() <= 1 {
+                break;
+            }
+
+            let (word, mask) = locate(self.bin_len, position);
+            let splits: Vec<(usize, usize, usize)> = ranges
+                .iter()
+                .map(|&(lo, hi)| {
+                    let split = lo
+                        + order[lo..hi].partition_point(|idx| self.reading(*idx)[word] & mask == 0);
+                    (lo, split, hi)
+                })
+                .collect();
+
+            let zeros = splits.iter().map(|(lo, split, _)| split - lo).sum();
+            let ones = splits.iter().map(|(_, split, hi)| hi - split).sum();
+
+            // Same as keep: an empty side means keep everyone
+            let (keep_zeros, keep_ones) = match criteria.select(zeros, ones) {
+                Some(Selection::Bit(0)) if zeros > 0 => (true, false),
+                Some(Selection::Bit(1)) if ones > 0 => (false, true),
+                Some(Selection::Both) => (true, true),
+                Some(_) => continue,
+                None => return Err(Self::tied(position)),
+            };
+
+            ranges.clear();
+            for (lo, split, hi) in splits {
+                if keep_zeros && split > lo {
+                    ranges.push((lo, split));
+                }
+                if keep_ones && hi > split {
+                    ranges.push((split, hi));
+                }
+            }
+
+            if let [(lo, hi)] = ranges[..] {
+                if hi - lo == 1 {
+                    resolved_at = Some(position);
+                }
+            }
+        }
+
+        // Different ranges differ on the position that split them. Within a
+        // range they're sorted, so if the ends match so does the middle.
+        let (lo, hi) = ranges[0];
+        if ranges.len() > 1 || self.reading(order[lo]) != self.reading(order[hi - 1]) {
+            let left = ranges.iter().map(|(lo, hi)| hi - lo).sum();
+            return Err(Self::unresolved(left));
+        }
+
+        Ok(Rating {
+            value: self.bits(order[lo]),
+            resolved_at,
+        })
+    }
+
+    /// The order [DiagnosticReport::rating_partitioned] needs.
+    pub fn sorted_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_unstable_by(|a, b| {
+            let (a, b) = (self.reading(*a), self.reading(*b));
+            a.iter().rev().cmp(b.iter().rev())
+        });
+
+        order
+    }
+
+    pub fn life_support(
+        self,
+        method: RatingMethod,
+        o2: BitCriteria,
+        co2: BitCriteria,
+    ) -> Result<(Rating, Rating), String> {
+        match method {
+            RatingMethod::Original => {
+                if (o2, co2) != (BitCriteria::MOST_COMMON, BitCriteria::LEAST_COMMON) {
+                    return Err("the original method only knows most:1 and least:0".into());
+                }
+
+                self.o2_co2()
+                    .ok_or_else(|| "the original method filtered every co2 reading out".into())
+            }
+            RatingMethod::Filter => Ok((self.clone().rating(o2)?, self.rating(co2)?)),
+            RatingMethod::Partition => {
+                let order = self.sorted_order();
+                Ok((
+                    self.rating_partitioned(&order, o2)?,
+                    self.rating_partitioned(&order, co2)?,
+                ))
+            }
+        }
+    }
+}
+
+/// Whether [DiagnosticReport::filter_until_one] drops the readings without
+/// the bit it picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Narrow {
+    UntilOne,
+    Never,
+}
 
-                if dr_co2.bin.len() == 1 {
-                    co2 = dr_co2.bin.get(0).map(|v| v.to_owned());
+/// What [DiagnosticReport::filter_until_one] picked at each position it got
+/// to, which are zero past where it stopped, the readings it left, and the
+/// position that left just one.
+#[derive(Debug, Clone)]
+struct Filtered {
+    picked: Bits,
+    left: DiagnosticReport,
+    resolved_at: Option<usize>,
+}
+
+/// A rating and the position whose filter left it as the only reading. The
+/// position is None if it was alone from the start, or if it had identical
+/// twins that made it through every position with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rating {
+    value: Bits,
+    resolved_at: Option<usize>,
+}
+
+/// What [DiagnosticReport::keep] did, for the events file. A filter that
+/// would keep nothing keeps everyone, so `kept` can be 0 with nothing lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportEvent {
+    Filtered {
+        position: usize,
+        bit: u8,
+        kept: usize,
+        from: usize,
+    },
+    /// What [DiagnosticReport::filter_common_uncommon] did
+    Split {
+        position: usize,
+        common: u8,
+        commons: usize,
+        uncommons: usize,
+    },
+}
+
+impl Event for ReportEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            ReportEvent::Filtered { .. } => "filtered",
+            ReportEvent::Split { .. } => "split",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        match *self {
+            ReportEvent::Filtered {
+                position,
+                bit,
+                kept,
+                from,
+            } => vec![
+                ("position", position.into()),
+                ("bit", bit.into()),
+                ("kept", kept.into()),
+                ("from", from.into()),
+            ],
+            ReportEvent::Split {
+                position,
+                common,
+                commons,
+                uncommons,
+            } => vec![
+                ("position", position.into()),
+                ("common", common.into()),
+                ("commons", commons.into()),
+                ("uncommons", uncommons.into()),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    Bit(u8),
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keep {
+    MostCommon,
+    LeastCommon,
+}
+
+/// What to do when there are as many zeros as ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tie {
+    Zero,
+    One,
+    KeepBoth,
+    Error,
+}
+
+/// Which bit to keep out of a column. Parses from `most` or `least` with an
+/// optional tie rule after a colon: `0`, `1`, `both` or `error`. Like
+/// `least:1` or `most:error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BitCriteria {
+    keep: Keep,
+    tie: Tie,
+}
+
+impl BitCriteria {
+    /// What the puzzle uses for gamma and o2.
+    pub const MOST_COMMON: Self = Self {
+        keep: Keep::MostCommon,
+        tie: Tie::One,
+    };
+
+    /// What the puzzle uses for epsilon and co2.
+    pub const LEAST_COMMON: Self = Self {
+        keep: Keep::LeastCommon,
+        tie: Tie::Zero,
+    };
+
+    /// None if it's a tie and we were told to error.
+    pub fn select(&self, zeros: usize, ones: usize) -> Option<Selection> {
+        let (common, uncommon) = match zeros.cmp(&ones) {
+            Ordering::Greater => (0, 1),
+            Ordering::Less => (1, 0),
+            Ordering::Equal => {
+                return match self.tie {
+                    Tie::Zero => Some(Selection::Bit(0)),
+                    Tie::One => Some(Selection::Bit(1)),
+                    Tie::KeepBoth => Some(Selection::Both),
+                    Tie::Error => None,
                 }
             }
+        };
+
+        match self.keep {
+            Keep::MostCommon => Some(Selection::Bit(common)),
+            Keep::LeastCommon => Some(Selection::Bit(uncommon)),
         }
+    }
+}
+
+impl FromStr for BitCriteria {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (keep, tie) = match s.split_once(':') {
+            Some((keep, tie)) => (keep, Some(tie)),
+            None => (s, None),
+        };
+
+        let (keep, default_tie) = match keep {
+            "most" => (Keep::MostCommon, Tie::One),
+            "least" => (Keep::LeastCommon, Tie::Zero),
+            _ => return Err(format!("'{}' is not most or least", keep)),
+        };
+
+        let tie = match tie {
+            None => default_tie,
+            Some("0") => Tie::Zero,
+            Some("1") => Tie::One,
+            Some("both") => Tie::KeepBoth,
+            Some("error") => Tie::Error,
+            Some(tie) => return Err(format!("'{}' is not a tie rule", tie)),
+        };
+
+        Ok(Self { keep, tie })
+    }
+}
+
+/// How to find the o2 and co2 ratings. Pick with `--method filter`,
+/// `--method partition` or `--method original`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum RatingMethod {
+    /// What we started with, see [DiagnosticReport::o2_co2].
+    Original,
+    /// Copy the report down to the matching readings for every position.
+    #[default]
+    Filter,
+    /// Sort once and narrow a range, see [DiagnosticReport::rating_partitioned].
+    Partition,
+}
 
-        (o2.unwrap(), co2.unwrap())
+impl FromStr for RatingMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "original" => Ok(Self::Original),
+            "filter" => Ok(Self::Filter),
+            "partition" => Ok(Self::Partition),
+            _ => Err(format!("'{}' is not a rating method", s)),
+        }
     }
 }
 
@@ -136,33 +649,42 @@ impl FromStr for DiagnosticReport {
     type Err = String;
 
     fn from_str(raw: &str) -> Result<Self, Self::Err> {
-        let len = raw.lines().next().unwrap().len();
-
-        Ok(Self {
-            /// Beware: From MSB (least on right) to LSB (least on left)
-            bin: raw
-                .lines()
-                .map(|s| {
-                    s.chars()
-                        .map(|c| {
-                            if c == '0' {
-                                0
-                            } else if c == '1' {
-                                1
-                            } else {
-                                panic!()
-                            }
-                        })
-                        .collect()
-                })
-                .collect(),
-            bin_len: len,
-        })
+        let len = match raw.lines().next() {
+            Some(line) => line.len(),
+            None => return Err("report has no readings".into()),
+        };
+        let mut report = Self::empty(len);
+
+        for (ln, line) in raw.lines().enumerate() {
+            if line.len() != len {
+                return Err(format!(
+                    "reading {} is {} digits but the first was {}",
+                    ln,
+                    line.len(),
+                    len
+                ));
+            }
+
+            let start = report.words.len();
+            report.words.resize(start + report.stride, 0);
+            for (position, c) in line.chars().enumerate() {
+                let (word, mask) = locate(len, position);
+                match c {
+                    '0' => (),
+                    '1' => report.words[start + word] |= mask,
+                    _ => return Err(format!("reading {} has non-binary digit '{}'", ln, c)),
+                }
+            }
+        }
+
+        Ok(report)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use aoc2021::rng::Rng;
+
     use super::*;
 
     fn dr_demo() -> DiagnosticReport {
@@ -197,35 +719,278 @@ mod test {
         assert_eq!(demo.zeros_ones(1), (1, 3));
         assert_eq!(demo.zeros_ones(2), (2, 2));
         assert_eq!(demo.zeros_ones(3), (3, 1));
+        assert_eq!(demo.column_ones(), vec![4, 3, 2, 1]);
     }
 
     #[test]
     fn diagnostic_report_has_wrong_common() {
         let demo = dr_demo();
 
-        assert_eq!(demo.common(0), (1, 0));
-        assert_eq!(demo.common(1), (1, 0));
-        assert_eq!(demo.common(2), (1, 0));
-        assert_eq!(demo.common(3), (0, 1));
+        let common = |position| {
+            let most = demo.select(position, BitCriteria::MOST_COMMON).unwrap();
+            let least = demo.select(position, BitCriteria::LEAST_COMMON).unwrap();
+            (most, least)
+        };
+
+        assert_eq!(common(0), (Selection::Bit(1), Selection::Bit(0)));
+        assert_eq!(common(1), (Selection::Bit(1), Selection::Bit(0)));
+        assert_eq!(common(2), (Selection::Bit(1), Selection::Bit(0)));
+        assert_eq!(common(3), (Selection::Bit(0), Selection::Bit(1)));
     }
 
     #[test]
     fn diagnostic_report_test_has_wrong_common_0() {
         let test = dr_test();
 
-        assert_eq!(test.common(0), (1, 0));
+        assert_eq!(
+            test.select(0, BitCriteria::MOST_COMMON),
+            Ok(Selection::Bit(1))
+        );
     }
 
     #[test]
     fn diagnostic_report_test_has_wrong_o2_co2() {
         let test = dr_test();
-        let (test_o2, test_co2) = test.o2_co2();
-
-        let mut o2 = vec![1, 0, 1, 1, 1];
+        let (test_o2, test_co2) = test
+            .life_support(
+                RatingMethod::Filter,
+                BitCriteria::MOST_COMMON,
+                BitCriteria::LEAST_COMMON,
+            )
+            .unwrap();
+        let (test_o2, test_co2) = (test_o2.value, test_co2.value);
+
+        let o2 = vec![1, 0, 1, 1, 1];
         let o2_dec = 23;
-        let o2_dec_test = msb_into_dec(&o2);
+        let o2_dec_test = Bits::from_msb(&o2).to_usize().unwrap();
 
-        assert_eq!(o2, test_o2);
+        assert_eq!(o2, test_o2.to_msb());
         assert_eq!(o2_dec, o2_dec_test);
+        assert_eq!(test_co2.to_usize(), Some(10));
+    }
+
+    #[test]
+    fn filtering_picks_wrong_bits() {
+        let never = dr_test()
+            .filter_until_one(BitCriteria::MOST_COMMON, Narrow::Never)
+            .unwrap();
+        assert_eq!(never.picked.to_string(), "10110");
+        assert_eq!(never.left.len(), 12);
+        assert_eq!(never.resolved_at, None);
+
+        // o2 is left alone at the last position, so every bit was picked
+        let until_one = dr_test()
+            .filter_until_one(BitCriteria::MOST_COMMON, Narrow::UntilOne)
+            .unwrap();
+        assert_eq!(until_one.picked.to_string(), "10111");
+        assert_eq!(until_one.left.len(), 1);
+        assert_eq!(until_one.resolved_at, Some(4));
+
+        // co2 is alone after 3 positions and the rest are never picked
+        let co2 = dr_test()
+            .filter_until_one(BitCriteria::LEAST_COMMON, Narrow::UntilOne)
+            .unwrap();
+        assert_eq!(co2.picked.to_string(), "01000");
+        assert_eq!(co2.left.bits(0).to_string(), "01010");
+        assert_eq!(co2.resolved_at, Some(2));
+
+        let both: BitCriteria = "most:both".parse().unwrap();
+        assert!(dr_demo().filter_until_one(both, Narrow::Never).is_err());
+    }
+
+    #[test]
+    fn bits_have_wrong_endianness() {
+        let bits = Bits::from_msb(&[1, 1, 0, 0, 1]);
+
+        assert_eq!(bits.to_u64(), Some(0b11001));
+        assert_eq!(bits, Bits::from_lsb(&[1, 0, 0, 1, 1]));
+        assert_eq!(bits.not().to_u64(), Some(0b00110));
+        assert_eq!(bits.to_string(), "11001");
+    }
+
+    #[test]
+    fn wide_report_has_wrong_gamma_epsilon() {
+        // 70 digits wide so the first six land in a second word
+        let one = format!("110000{}", "0".repeat(63) + "1");
+        let two = format!("100000{}", "0".repeat(63) + "1");
+        let three = format!("010000{}", "0".repeat(64));
+        let dr = DiagnosticReport::from_str(&[one, two, three].join("\n")).unwrap();
+
+        assert_eq!(dr.stride, 2);
+        assert_eq!(dr.zeros_ones(0), (1, 2));
+        assert_eq!(dr.zeros_ones(69), (1, 2));
+
+        let (gamma, epsilon) = dr.gamma_epsilon_bits();
+        assert_eq!(gamma.to_string(), format!("110000{}", "0".repeat(63) + "1"));
+        assert_eq!(gamma.to_u64(), None);
+        assert_eq!(epsilon, gamma.not());
+        assert_eq!(
+            epsilon.to_string(),
+            format!("001111{}", "1".repeat(63) + "0")
+        );
+    }
+
+    #[test]
+    fn partitioned_ratings_disagree_with_filtered() {
+        let mut rng = Rng::new(3);
+        let keeps = [Keep::MostCommon, Keep::LeastCommon];
+        let ties = [Tie::Zero, Tie::One, Tie::KeepBoth, Tie::Error];
+
+        for _ in 0..1000 {
+            let width = rng.below(70) as usize + 1;
+            let count = rng.below(200) as usize + 1;
+            let raw: Vec<String> = (0..count)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| if rng.below(2) == 0 { '0' } else { '1' })
+                        .collect()
+                })
+                .collect();
+            let dr = DiagnosticReport::from_str(&raw.join("\n")).unwrap();
+            let criteria = BitCriteria {
+                keep: keeps[rng.below(2) as usize],
+                tie: ties[rng.below(4) as usize],
+            };
+
+            assert_eq!(
+                dr.rating_partitioned(&dr.sorted_order(), criteria),
+                dr.clone().rating(criteria),
+                "{:?} on report:\n{}",
+                criteria,
+                raw.join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn ratings_disagree_with_original() {
+        let mut rng = Rng::new(27);
+        let mut emptied = 0;
+
+        for _ in 0..1000 {
+            let width = rng.below(20) as usize + 1;
+            let count = rng.below(100) as usize + 1;
+            let raw: Vec<String> = (0..count)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| if rng.below(2) == 0 { '0' } else { '1' })
+                        .collect()
+                })
+                .collect();
+            let dr = DiagnosticReport::from_str(&raw.join("\n")).unwrap();
+
+            let (o2, co2) = match dr.clone().o2_co2() {
+                Some(ratings) => ratings,
+                None => {
+                    emptied += 1;
+                    continue;
+                }
+            };
+            for method in [RatingMethod::Filter, RatingMethod::Partition] {
+                let (new_o2, new_co2) = dr
+                    .clone()
+                    .life_support(method, BitCriteria::MOST_COMMON, BitCriteria::LEAST_COMMON)
+                    .unwrap();
+                assert_eq!(
+                    (&new_o2.value, &new_co2.value),
+                    (&o2.value, &co2.value),
+                    "{:?} on report:\n{}",
+                    method,
+                    raw.join("\n")
+                );
+            }
+        }
+
+        // Identical readings do it, so it's not rare
+        assert!(emptied > 0);
+    }
+
+    #[test]
+    fn same_co2_bits_are_filtered_wrong() {
+        // Every reading has ones in the first two positions, so there's no
+        // uncommon bit to keep. The original keeps nobody and gives up; the
+        // others keep both and the tie at the end picks 110.
+        let dr = DiagnosticReport::from_str("110\n111").unwrap();
+        assert!(dr.clone().o2_co2().is_none());
+
+        for method in [RatingMethod::Filter, RatingMethod::Partition] {
+            let (o2, co2) = dr
+                .clone()
+                .life_support(method, BitCriteria::MOST_COMMON, BitCriteria::LEAST_COMMON)
+                .unwrap();
+            assert_eq!(o2.value.to_string(), "111");
+            assert_eq!(co2.value.to_string(), "110");
+            assert_eq!(co2.resolved_at, Some(2));
+        }
+    }
+
+    #[test]
+    fn partitioned_o2_co2_are_wrong() {
+        let dr: DiagnosticReport = day_parse!();
+        let (o2, co2) = dr
+            .life_support(
+                RatingMethod::Partition,
+                BitCriteria::MOST_COMMON,
+                BitCriteria::LEAST_COMMON,
+            )
+            .unwrap();
+
+        assert_eq!(o2.value.to_usize(), Some(2815));
+        assert_eq!(co2.value.to_usize(), Some(1059));
+    }
+
+    #[test]
+    fn test_ratings_resolve_at_wrong_position() {
+        let test = dr_test();
+
+        let o2 = test.clone().rating(BitCriteria::MOST_COMMON).unwrap();
+        let co2 = test.clone().rating(BitCriteria::LEAST_COMMON).unwrap();
+        assert_eq!(o2.resolved_at, Some(4));
+        assert_eq!(co2.resolved_at, Some(2));
+
+        // The o2 filter ends on a tie between 10111 and 10110
+        let strict = "most:error".parse().unwrap();
+        assert!(test.clone().rating(strict).is_err());
+
+        // ...and keeping both leaves them both
+        let both = "most:both".parse().unwrap();
+        assert!(test.rating(both).is_err());
+    }
+
+    #[test]
+    fn co2_filter_events_are_wrong() {
+        let events = aoc2021::events::capture(|| {
+            dr_test().rating(BitCriteria::LEAST_COMMON).unwrap();
+        });
+
+        let expected = [
+            r#""event":"filtered","position":0,"bit":0,"kept":5,"from":12}"#,
+            r#""event":"filtered","position":1,"bit":1,"kept":2,"from":5}"#,
+            r#""event":"filtered","position":2,"bit":0,"kept":1,"from":2}"#,
+        ];
+        assert_eq!(events.len(), expected.len());
+        for (event, expected) in events.iter().zip(expected) {
+            assert!(event.starts_with(r#"{"day":"day3","seq":"#), "{}", event);
+            assert!(event.ends_with(expected), "{}", event);
+        }
+    }
+
+    #[test]
+    fn original_split_events_are_wrong() {
+        let events = aoc2021::events::capture(|| {
+            dr_test().o2_co2().unwrap();
+        });
+
+        // o2 and co2 both split on position 0, then take turns
+        let expected = [
+            r#""event":"split","position":0,"common":1,"commons":7,"uncommons":5}"#,
+            r#""event":"split","position":0,"common":1,"commons":7,"uncommons":5}"#,
+            r#""event":"split","position":1,"common":0,"commons":4,"uncommons":3}"#,
+            r#""event":"split","position":1,"common":0,"commons":3,"uncommons":2}"#,
+        ];
+        for (event, expected) in events.iter().zip(expected) {
+            assert!(event.ends_with(expected), "{}", event);
+        }
+        assert_eq!(events.len(), 8);
     }
 }
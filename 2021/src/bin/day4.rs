@@ -1,117 +1,1058 @@
-#![feature(stmt_expr_attributes)]
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    hash::Hash,
+    str::FromStr,
+    time::Instant,
+};
 
-use std::str::FromStr;
-
-use aoc2021::day_parse;
+use aoc2021::{
+    arg_flag, arg_value,
+    bigint::BigUint,
+    day_parse, debug,
+    debugger::{self, Condition, Inspect},
+    error, event,
+    events::{self, Event, Value},
+    info,
+    rng::Rng,
+    simulation::Simulation,
+    term::{self, Style},
+    trace, AdventError,
+};
 
 fn main() {
-    let mut game: BingoGame = day_parse!();
-    let (winner, final_drawn) = game.clone().draw_until_winner();
-    let unmarked: usize = winner.unmarked().iter().map(|v| *v as usize).sum();
+    let _events = events::FlushGuard;
+    if let Some(count) = arg_value("--bench").unwrap() {
+        bench(count);
+        return;
+    }
+
+    let game: BingoGame<u32> = day_parse!();
+    let patterns: WinPatterns = arg_value("--win").unwrap().unwrap_or_default();
+    let mut game = game.with_patterns(&patterns);
+    let exact = arg_flag("--exact");
+
+    if arg_flag("--debug") {
+        let stdin = std::io::stdin();
+        debugger::run(
+            game,
+            stdin.lock(),
+            std::io::stdout(),
+            term::width(),
+            term::stdout_colour(),
+        )
+        .unwrap();
+        return;
+    }
+
+    if let Some(trials) = arg_value("--monte-carlo").unwrap() {
+        let threads = arg_value("--threads")
+            .unwrap()
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1);
+        let seed = arg_value("--seed").unwrap().unwrap_or(4);
+
+        let before = Instant::now();
+        let stats = game.monte_carlo(trials, threads, seed);
+        print!("{}", stats);
+        info!(
+            "{} threads took {} millisec",
+            threads,
+            before.elapsed().as_millis()
+        );
+        return;
+    }
+
+    if arg_flag("--timeline") {
+        for win in game.clone().timeline() {
+            println!("{}", win);
+        }
+    }
+
+    let winner = match winner_arg() {
+        Ok(winner) => winner,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(k) = winner {
+        match game.clone().winner(k - 1) {
+            Some(win) => println!("Winner {}: {}", k, win),
+            None => println!("Only {} boards ever win", game.clone().wins().count()),
+        }
+    }
+
+    let show = arg_flag("--show");
+    let colour = term::stdout_colour();
+
+    let mut first_game = game.clone();
+    let first = first_game.draw_until_winner().expect("nobody won");
+    if show {
+        println!("{}", first_game.render(term::width(), colour));
+    }
 
     println!(
-        "Unmarked sum {}, last drawn {}. Product {}",
-        unmarked,
-        final_drawn,
-        unmarked * final_drawn as usize
+        "Unmarked sum {}, last drawn {}, won with {}. Product {}",
+        first.unmarked,
+        first.number,
+        first.line,
+        first.score(exact)
     );
 
-    let (winner, final_drawn) = game.find_last_win();
-    let unmarked: usize = winner.unmarked().iter().map(|v| *v as usize).sum();
+    let last = game.find_last_win().expect("nobody won");
+    if show {
+        println!("\n{}", game.render(term::width(), colour));
+    }
+
+    println!(
+        "LAST WIN\nUnmarked sum {}, last drawn {}, won with {}. Product {}",
+        last.unmarked,
+        last.number,
+        last.line,
+        last.score(exact)
+    );
+}
+
+/// `--winner`, which counts from 1.
+fn winner_arg() -> Result<Option<usize>, AdventError> {
+    match arg_value("--winner")? {
+        Some(0) => Err(AdventError::Argument {
+            flag: "--winner".to_owned(),
+            inner: "winners count from 1".into(),
+        }),
+        k => Ok(k),
+    }
+}
+
+/// With `exact` we multiply as [BigUint]s so big boards can't overflow.
+fn score<T: BingoNumber>(unmarked: u64, final_drawn: T, exact: bool) -> String {
+    let final_drawn: u64 = final_drawn.into();
+
+    if exact {
+        (BigUint::from(unmarked) * BigUint::from(final_drawn)).to_string()
+    } else {
+        (unmarked * final_drawn).to_string()
+    }
+}
+
+/// What can be written on a board. The puzzle fits in a u8, but nothing
+/// stops a board from being bigger than that.
+trait BingoNumber: Copy + Eq + Hash + FromStr + fmt::Display + Into<u64> {}
+
+impl<T: Copy + Eq + Hash + FromStr + fmt::Display + Into<u64>> BingoNumber for T {}
+
+/// A kind of line that wins a board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum WinPattern {
+    Rows,
+    Columns,
+    /// Corner to corner both ways. Only square boards have them.
+    Diagonals,
+    /// All four corners at once
+    Corners,
+    /// Both diagonals at once. Only square boards have one.
+    X,
+    /// The whole card
+    Blackout,
+}
+
+impl WinPattern {
+    /// Every line of this kind on a `rows` by `cols` board, as the cells in
+    /// each. Some boards don't have any.
+    pub fn lines(self, rows: usize, cols: usize) -> Vec<Vec<usize>> {
+        let square = rows == cols;
+        let down: Vec<usize> = (0..rows).map(|i| i * cols + i).collect();
+        let up: Vec<usize> = (0..rows).map(|i| i * cols + cols - 1 - i).collect();
+
+        match self {
+            WinPattern::Rows => (0..rows)
+                .map(|row| (0..cols).map(|col| row * cols + col).collect())
+                .collect(),
+            WinPattern::Columns => (0..cols)
+                .map(|col| (0..rows).map(|row| row * cols + col).collect())
+                .collect(),
+            WinPattern::Diagonals if square => vec![down, up],
+            WinPattern::Corners => {
+                let mut corners = vec![0, cols - 1, (rows - 1) * cols, rows * cols - 1];
+                corners.sort_unstable();
+                corners.dedup();
+                vec![corners]
+            }
+            WinPattern::X if square => {
+                let mut x: Vec<usize> = down.into_iter().chain(up).collect();
+                x.sort_unstable();
+                x.dedup();
+                vec![x]
+            }
+            WinPattern::Blackout => vec![(0..rows * cols).collect()],
+            WinPattern::Diagonals | WinPattern::X => vec![],
+        }
+    }
+}
+
+impl FromStr for WinPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rows" => Ok(WinPattern::Rows),
+            "columns" | "cols" => Ok(WinPattern::Columns),
+            "diagonals" => Ok(WinPattern::Diagonals),
+            "corners" => Ok(WinPattern::Corners),
+            "x" => Ok(WinPattern::X),
+            "blackout" | "full" => Ok(WinPattern::Blackout),
+            _ => Err(format!("'{}' isn't a win pattern", s)),
+        }
+    }
+}
+
+/// Which lines win a game, and whether the middle of the board starts off
+/// marked. Parses from a list like `rows,columns,diagonals,free`.
+#[derive(Clone, Debug, PartialEq)]
+struct WinPatterns {
+    patterns: Vec<WinPattern>,
+    /// Only boards with an odd number of rows and columns have a centre
+    free_centre: bool,
+}
+
+impl Default for WinPatterns {
+    fn default() -> Self {
+        Self {
+            patterns: vec![WinPattern::Rows, WinPattern::Columns],
+            free_centre: false,
+        }
+    }
+}
+
+impl FromStr for WinPatterns {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut patterns = vec![];
+        let mut free_centre = false;
+
+        for name in s.split(',').map(str::trim) {
+            match name {
+                "free" => free_centre = true,
+                _ => patterns.push(name.parse()?),
+            }
+        }
 
+        if patterns.is_empty() {
+            return Err("there has to be at least one way to win".into());
+        }
+
+        Ok(Self {
+            patterns,
+            free_centre,
+        })
+    }
+}
+
+/// One way to win on a particular board.
+#[derive(Clone, Debug, PartialEq)]
+struct Line {
+    pattern: WinPattern,
+    /// Which row, column, or diagonal, counting from zero
+    which: usize,
+    cells: Vec<usize>,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.pattern, self.which) {
+            (WinPattern::Rows, row) => write!(f, "row {}", row),
+            (WinPattern::Columns, col) => write!(f, "column {}", col),
+            (WinPattern::Diagonals, 0) => write!(f, "top-left diagonal"),
+            (WinPattern::Diagonals, _) => write!(f, "top-right diagonal"),
+            (WinPattern::Corners, _) => write!(f, "four corners"),
+            (WinPattern::X, _) => write!(f, "X"),
+            (WinPattern::Blackout, _) => write!(f, "blackout"),
+        }
+    }
+}
+
+/// Make `count` random boards and a random draw and play the whole game out
+/// three times: scanning every board for every draw like we used to, asking
+/// every board's own index, and going straight to the right boards with the
+/// game's index.
+fn bench(count: usize) {
+    let mut rng = Rng::new(4);
+    let mut numbers: Vec<u8> = (0..100).collect();
+
+    let mut boards = vec![];
+    for _ in 0..count {
+        rng.shuffle(&mut numbers);
+        boards.push(Board::new(5, 5, numbers[..25].to_vec()));
+    }
+    rng.shuffle(&mut numbers);
+    let game = BingoGame::new(numbers.clone(), boards);
+
+    let before = Instant::now();
+    let mut scanned = game.boards.clone();
+    let mut scan_wins = 0;
+    for draw in &game.drawing {
+        for board in &mut scanned {
+            board.drawn_scan(*draw);
+            if board.check_won_scan() {
+                scan_wins += 1;
+            }
+        }
+    }
+    let scan_time = before.elapsed();
+
+    let before = Instant::now();
+    let mut per_board = game.boards.clone();
+    let mut board_wins = 0;
+    for draw in &game.drawing {
+        for board in &mut per_board {
+            board.drawn(*draw);
+            if board.check_won() {
+                board_wins += 1;
+            }
+        }
+    }
+    let board_time = before.elapsed();
+
+    // The scans count every won board again on every draw, so a win on draw
+    // `d` is worth a point for each draw from `d` on. Only the wins the
+    // index reports are looked at, not every board.
+    let before = Instant::now();
+    let mut indexed = game;
+    let draws = indexed.drawing.len();
+    let index_wins: usize = indexed.wins().map(|win| draws - win.draw).sum();
+    let index_time = before.elapsed();
+
+    assert_eq!(scan_wins, board_wins, "scanning and board indexes disagree");
+    assert_eq!(
+        scan_wins, index_wins,
+        "scanning and the game index disagree"
+    );
     println!(
-        "LAST WIN\nUnmarked sum {}, last drawn {}. Product {}",
-        unmarked,
-        final_drawn,
-        unmarked * final_drawn as usize
+        "{} boards, {} draws\nScanning {} nanosec\nBoard indexes {} nanosec\nGame index {} nanosec",
+        count,
+        numbers.len(),
+        scan_time.as_nanos(),
+        board_time.as_nanos(),
+        index_time.as_nanos()
     );
 }
 
 #[derive(Clone)]
-struct BingoGame {
-    drawing: Vec<u8>,
+struct BingoGame<T = u8> {
+    drawing: Vec<T>,
+    /// How many numbers of `drawing` have been drawn so far
+    drawn: usize,
 
-    boards: Vec<Board>,
+    boards: Vec<Board<T>>,
+    /// Every place each number is, as (board, cell)
+    index: HashMap<T, Vec<(usize, usize)>>,
 }
 
-impl BingoGame {
-    pub fn draw_until_winner(&mut self) -> (Board, u8) {
-        for draw in &self.drawing {
-            for board in &mut self.boards {
-                board.drawn(*draw);
+impl<T: BingoNumber> BingoGame<T> {
+    pub fn new(drawing: Vec<T>, boards: Vec<Board<T>>) -> Self {
+        let mut index: HashMap<T, Vec<(usize, usize)>> = HashMap::new();
+        for (board_idx, board) in boards.iter().enumerate() {
+            for (cell, num) in board.data.iter().enumerate() {
+                index.entry(*num).or_default().push((board_idx, cell));
+            }
+        }
 
-                if board.check_won() {
-                    return (board.clone(), *draw);
-                }
+        Self {
+            drawing,
+            drawn: 0,
+            boards,
+            index,
+        }
+    }
+
+    /// Change how every board wins. Best done before anything's drawn.
+    pub fn with_patterns(mut self, patterns: &WinPatterns) -> Self {
+        for board in &mut self.boards {
+            board.set_patterns(patterns);
+        }
+
+        self
+    }
+
+    /// Draw until someone wins. The game is left at that draw.
+    pub fn draw_until_winner(&mut self) -> Option<WinEvent<T>> {
+        self.wins().next()
+    }
+
+    /// Draw until everyone's won. The game is left at the draw the last
+    /// board won on. If more than one board won on that draw it's the first
+    /// of them, like [BingoGame::draw_until_winner].
+    pub fn find_last_win(&mut self) -> Option<WinEvent<T>> {
+        let mut last: Option<WinEvent<T>> = None;
+        for win in self.wins() {
+            if last.as_ref().is_none_or(|last| last.draw != win.draw) {
+                last = Some(win);
             }
         }
 
-        unreachable!()
+        last
     }
 
-    pub fn find_last_win(&mut self) -> (Board, u8) {
-        let drawing = self.drawing.clone();
+    /// The `k`th board to win counting from zero, so `winner(0)` is the
+    /// same as [BingoGame::draw_until_winner].
+    pub fn winner(&mut self, k: usize) -> Option<WinEvent<T>> {
+        self.wins().nth(k)
+    }
+
+    /// Every win from here on in the order they happen.
+    pub fn timeline(&mut self) -> Vec<WinEvent<T>> {
+        self.wins().collect()
+    }
+
+    /// Draws lazily, only as far as it needs to to find the next win. Boards
+    /// that had already won before this was called aren't in it. Boards that
+    /// win on the same draw come in the order they're in.
+    ///
+    /// It steps the game itself rather than with a
+    /// [Runner](aoc2021::simulation::Runner): after each draw it only looks
+    /// at the boards with that number on them, and a Runner's predicate
+    /// doesn't know what was drawn.
+    pub fn wins(&mut self) -> Wins<'_, T> {
+        Wins {
+            won: self.boards.iter().map(Board::check_won).collect(),
+            pending: VecDeque::new(),
+            game: self,
+        }
+    }
 
-        for draw in drawing {
-            println!("ahh {}", draw);
-            for board in &mut self.boards {
-                board.drawn(draw);
+    /// Every board with a title saying whether it's won, laid out side by
+    /// side as many as fit in `width` columns. See [Board::render] for how
+    /// the cells look.
+    pub fn render(&self, width: usize, colour: bool) -> String {
+        let blocks: Vec<Vec<String>> = self
+            .boards
+            .iter()
+            .enumerate()
+            .map(|(idx, board)| {
+                let title = match board.winning_line() {
+                    Some(line) => format!("board {}, won with {}", idx, line),
+                    None => format!("board {}", idx),
+                };
+
+                let mut block = vec![term::paint(&title, &[Style::Bold], colour)];
+                block.extend(board.render(colour));
+                block
+            })
+            .collect();
+
+        term::side_by_side(&blocks, width, 3)
+    }
+}
+
+/// A board winning.
+#[derive(Clone, Debug, PartialEq)]
+struct WinEvent<T> {
+    board: usize,
+    /// Where in the drawing the winning number was, counting from zero
+    draw: usize,
+    number: T,
+    line: Line,
+    /// The sum of the board's unmarked numbers when it won
+    unmarked: u64,
+}
+
+impl<T: BingoNumber> WinEvent<T> {
+    pub fn score(&self, exact: bool) -> String {
+        score(self.unmarked, self.number, exact)
+    }
+}
+
+impl<T: BingoNumber> fmt::Display for WinEvent<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "board {} won on draw {} ({}) with {}, scoring {}",
+            self.board,
+            self.draw,
+            self.number,
+            self.line,
+            self.score(false)
+        )
+    }
+}
+
+/// What happened in a game, for the events file. Draws and marks come from
+/// stepping, wins from [BingoGame::wins], so a game stepped by hand never
+/// says anyone won.
+enum BingoEvent<T> {
+    Draw {
+        draw: usize,
+        number: T,
+    },
+    Marked {
+        board: usize,
+        row: usize,
+        col: usize,
+    },
+    Won(WinEvent<T>),
+}
+
+impl<T: BingoNumber> Event for BingoEvent<T> {
+    fn kind(&self) -> &'static str {
+        match self {
+            BingoEvent::Draw { .. } => "draw",
+            BingoEvent::Marked { .. } => "marked",
+            BingoEvent::Won(_) => "won",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        match self {
+            BingoEvent::Draw { draw, number } => vec![
+                ("draw", (*draw).into()),
+                ("number", (*number).into().into()),
+            ],
+            BingoEvent::Marked { board, row, col } => vec![
+                ("board", (*board).into()),
+                ("row", (*row).into()),
+                ("col", (*col).into()),
+            ],
+            BingoEvent::Won(win) => vec![
+                ("board", win.board.into()),
+                ("draw", win.draw.into()),
+                ("number", win.number.into().into()),
+                ("line", win.line.to_string().into()),
+                ("score", Value::Number(win.score(true))),
+            ],
+        }
+    }
+}
+
+/// Made by [BingoGame::wins]. When more than one board wins on the same
+/// draw they come out in board order.
+struct Wins<'a, T> {
+    game: &'a mut BingoGame<T>,
+    won: Vec<bool>,
+    /// Wins from the last draw we haven't given out yet
+    pending: VecDeque<WinEvent<T>>,
+}
+
+impl<'a, T: BingoNumber> Iterator for Wins<'a, T> {
+    type Item = WinEvent<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            if self.won.iter().all(|w| *w) || self.game.drawn == self.game.drawing.len() {
+                return None;
             }
 
-            // gen 2023-12-02: back in 2021 there was a drain_filter feature.
-            // this code was making r-a mad and I had to fix it, so I did this...
-            let mut lost: Vec<Board> = vec![];
-            let mut won: Vec<Board> = vec![];
-            for board in self.boards.drain(..) {
-                if board.check_won() {
-                    won.push(board);
-                } else {
-                    lost.push(board);
+            let draw = self.game.drawn;
+            let number = self.game.drawing[draw];
+            self.game.step();
+            trace!("draw {} is {}", draw, number);
+
+            // Only the boards with this number on them can have just won
+            let mut boards: Vec<usize> = self
+                .game
+                .index
+                .get(&number)
+                .into_iter()
+                .flatten()
+                .map(|(board, _)| *board)
+                .collect();
+            boards.sort_unstable();
+            boards.dedup();
+
+            for idx in boards {
+                let board = &self.game.boards[idx];
+                if !self.won[idx] && board.check_won() {
+                    self.won[idx] = true;
+                    debug!("board {} won on draw {} ({})", idx, draw, number);
+                    let win = WinEvent {
+                        board: idx,
+                        draw,
+                        number,
+                        line: board.winning_line().unwrap().clone(),
+                        unmarked: board.unmarked_sum(),
+                    };
+                    event!(BingoEvent::Won(win.clone()));
+                    self.pending.push_back(win);
                 }
             }
-            self.boards = lost;
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+impl<T: BingoNumber + Send + Sync> BingoGame<T> {
+    /// Play `trials` games with these boards and the drawing shuffled, split
+    /// over `threads` threads. Every game gets its own [Rng] seeded from
+    /// `seed` and which game it is, so the answer doesn't depend on how many
+    /// threads there are. Games start from wherever this one is, so it wants
+    /// to be a game nothing's been drawn in yet.
+    pub fn monte_carlo(&self, trials: u64, threads: usize, seed: u64) -> MonteCarlo {
+        let threads = threads.max(1) as u64;
+        let mut stats = MonteCarlo::new(self.boards.len(), self.drawing.len());
 
-            println!("Board count {}", self.boards.len());
-            if self.boards.len() == 0 {
-                return (won[0].clone(), draw);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|thread| {
+                    scope.spawn(move || {
+                        let mut stats = MonteCarlo::new(self.boards.len(), self.drawing.len());
+
+                        // Thousands of games' worth of events, all mixed
+                        // together, aren't any use to anyone
+                        events::muted(|| {
+                            for trial in (thread..trials).step_by(threads as usize) {
+                                let mut rng = Rng::new(seed.wrapping_add(trial));
+                                let mut game = self.clone();
+                                rng.shuffle(&mut game.drawing);
+
+                                stats.record(&game.timeline(), &mut rng);
+                            }
+                        });
+
+                        stats
+                    })
+                })
+                .collect();
+
+            for handle in handles {
+                stats.merge(&handle.join().unwrap());
             }
+        });
+
+        stats
+    }
+}
+
+/// What happened over a lot of games with the same boards and differently
+/// shuffled drawings. Everything's indexed by board.
+#[derive(Clone, Debug, PartialEq)]
+struct MonteCarlo {
+    trials: u64,
+    /// Games each board won first. When boards win on the same draw one of
+    /// them is picked at random, so this adds up to the games anyone won.
+    first: Vec<u64>,
+    /// Games each board won last, ties picked the same way
+    last: Vec<u64>,
+    /// Games each board won at all
+    wins: Vec<u64>,
+    /// The sum of the draws each board won on, counting from zero
+    win_draws: Vec<u64>,
+    /// How many games each board won on each draw
+    histogram: Vec<Vec<u64>>,
+}
+
+impl MonteCarlo {
+    pub fn new(boards: usize, draws: usize) -> Self {
+        Self {
+            trials: 0,
+            first: vec![0; boards],
+            last: vec![0; boards],
+            wins: vec![0; boards],
+            win_draws: vec![0; boards],
+            histogram: vec![vec![0; draws]; boards],
         }
+    }
+
+    /// Count one game. `rng` breaks ties.
+    pub fn record<T>(&mut self, timeline: &[WinEvent<T>], rng: &mut Rng) {
+        self.trials += 1;
+
+        for win in timeline {
+            self.wins[win.board] += 1;
+            self.win_draws[win.board] += win.draw as u64;
+            self.histogram[win.board][win.draw] += 1;
+        }
+
+        let mut pick = |draw: usize| {
+            let tied: Vec<usize> = timeline
+                .iter()
+                .filter(|w| w.draw == draw)
+                .map(|w| w.board)
+                .collect();
+            tied[rng.below(tied.len() as u64) as usize]
+        };
+
+        if let (Some(first), Some(last)) = (timeline.first(), timeline.last()) {
+            let first = pick(first.draw);
+            self.first[first] += 1;
+            let last = pick(last.draw);
+            self.last[last] += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &MonteCarlo) {
+        let add = |ours: &mut [u64], theirs: &[u64]| {
+            ours.iter_mut().zip(theirs).for_each(|(o, t)| *o += t);
+        };
+
+        self.trials += other.trials;
+        add(&mut self.first, &other.first);
+        add(&mut self.last, &other.last);
+        add(&mut self.wins, &other.wins);
+        add(&mut self.win_draws, &other.win_draws);
+        for (ours, theirs) in self.histogram.iter_mut().zip(&other.histogram) {
+            add(ours, theirs);
+        }
+    }
+
+    pub fn p_first(&self, board: usize) -> f64 {
+        self.first[board] as f64 / self.trials as f64
+    }
+
+    pub fn p_last(&self, board: usize) -> f64 {
+        self.last[board] as f64 / self.trials as f64
+    }
+
+    /// Which draw the board wins on on average, out of the games it won.
+    /// None if it never did.
+    pub fn mean_draw(&self, board: usize) -> Option<f64> {
+        match self.wins[board] {
+            0 => None,
+            wins => Some(self.win_draws[board] as f64 / wins as f64),
+        }
+    }
+
+    /// How many wins there were on each draw, all boards together.
+    pub fn total_histogram(&self) -> Vec<u64> {
+        let mut total = vec![0; self.histogram.first().map(Vec::len).unwrap_or(0)];
+        for board in &self.histogram {
+            total.iter_mut().zip(board).for_each(|(t, b)| *t += b);
+        }
+
+        total
+    }
+}
+
+impl fmt::Display for MonteCarlo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} games", self.trials)?;
+        writeln!(f, "board  first%   last%  mean draw")?;
+        for board in 0..self.wins.len() {
+            let mean = match self.mean_draw(board) {
+                Some(mean) => format!("{:.2}", mean),
+                None => "never".into(),
+            };
+
+            writeln!(
+                f,
+                "{:5} {:7.3} {:7.3} {:>10}",
+                board,
+                self.p_first(board) * 100.0,
+                self.p_last(board) * 100.0,
+                mean
+            )?;
+        }
+
+        // Five draws to a bar so a hundred draws fits on a screen
+        writeln!(f, "Wins by draw")?;
+        let buckets: Vec<u64> = self
+            .total_histogram()
+            .chunks(5)
+            .map(|chunk| chunk.iter().sum())
+            .collect();
+        let tallest = buckets.iter().copied().max().unwrap_or(0).max(1);
+        for (idx, count) in buckets.iter().enumerate() {
+            writeln!(
+                f,
+                "{:3}-{:<3} {:>8} {}",
+                idx * 5,
+                idx * 5 + 4,
+                count,
+                "#".repeat((count * 50 / tallest) as usize)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A step is one number drawn. Once they've all been drawn stepping does
+/// nothing.
+impl<T: BingoNumber> Simulation for BingoGame<T> {
+    /// How many have been drawn, and each board's marks and winning line
+    type Snapshot = (usize, Vec<(Vec<bool>, Option<usize>)>);
+
+    fn step(&mut self) {
+        if let Some(number) = self.drawing.get(self.drawn) {
+            event!(BingoEvent::Draw {
+                draw: self.drawn,
+                number: *number,
+            });
+
+            for (idx, cell) in self.index.get(number).into_iter().flatten() {
+                let board = &mut self.boards[*idx];
+                if !board.marks[*cell] {
+                    event!(BingoEvent::<T>::Marked {
+                        board: *idx,
+                        row: cell / board.cols,
+                        col: cell % board.cols,
+                    });
+                }
+                board.mark(*cell);
+            }
+
+            self.drawn += 1;
+        }
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        let marks = self
+            .boards
+            .iter()
+            .map(|b| (b.marks.clone(), b.won))
+            .collect();
+        (self.drawn, marks)
+    }
+}
+
+/// Conditions are `any won`, `all won`, `board K won` and `drawn N`, which
+/// is true once N has been drawn.
+impl<T: BingoNumber + 'static> Inspect for BingoGame<T> {
+    /// The same as the snapshot, there's nothing it leaves out
+    type State = <Self as Simulation>::Snapshot;
+
+    fn status(&self) -> String {
+        let won = match self.boards.iter().filter(|b| b.check_won()).count() {
+            0 => "no boards won".to_owned(),
+            1 => "1 board won".to_owned(),
+            n => format!("{} boards won", n),
+        };
+
+        match self.drawn {
+            0 => format!("nothing drawn yet, {}", won),
+            drawn => format!(
+                "draw {} was {}, {}",
+                drawn - 1,
+                self.drawing[drawn - 1],
+                won
+            ),
+        }
+    }
+
+    /// Every board, as many to a row as fit, see [BingoGame::render]
+    fn inspect(&self, width: usize, colour: bool) -> String {
+        self.render(width, colour)
+    }
+
+    fn finished(&self) -> bool {
+        self.drawn == self.drawing.len()
+    }
+
+    fn state(&self) -> Self::State {
+        self.snapshot()
+    }
+
+    fn restore(&mut self, (drawn, marks): &Self::State) {
+        self.drawn = *drawn;
+        for (board, (marks, won)) in self.boards.iter_mut().zip(marks) {
+            board.restore(marks, *won);
+        }
+    }
 
-        unreachable!()
+    fn condition(s: &str) -> Result<Condition<Self>, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+
+        match words[..] {
+            ["any", "won"] | ["won"] => Ok(Box::new(|game: &Self| {
+                game.boards.iter().any(Board::check_won)
+            })),
+            ["all", "won"] => Ok(Box::new(|game: &Self| {
+                game.boards.iter().all(Board::check_won)
+            })),
+            ["board", k, "won"] => {
+                let k: usize = k
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a board number", k))?;
+                Ok(Box::new(move |game: &Self| {
+                    game.boards.get(k).map(Board::check_won).unwrap_or(false)
+                }))
+            }
+            ["drawn", n] => {
+                let n: T = n
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a number that could be drawn", n))?;
+                Ok(Box::new(move |game: &Self| {
+                    game.drawing[..game.drawn].contains(&n)
+                }))
+            }
+            _ => Err(format!(
+                "'{}' isn't a condition. Try 'any won', 'all won', 'board K won' or 'drawn N'",
+                s
+            )),
+        }
     }
 }
 
-impl FromStr for BingoGame {
+/// The drawing on the first line, then boards separated by blank lines. A
+/// board's size is however many rows and columns it has, so they don't all
+/// have to be the same.
+impl<T: BingoNumber> FromStr for BingoGame<T> {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut lines = s.lines();
         let drawing = lines
             .next()
-            .unwrap()
+            .ok_or("there's no drawing")?
             .split(',')
-            .map(|s| u8::from_str_radix(s, 10).unwrap())
-            .collect();
+            .map(|s| {
+                s.trim()
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a number to draw", s.trim()))
+            })
+            .collect::<Result<_, _>>()?;
 
         let mut boards = vec![];
-        let lines: Vec<&str> = lines.collect();
-        for board in lines.chunks(6) {
-            let board: Vec<String> = board[1..].iter().map(|&s| s.to_owned()).collect();
-            boards.push(Board::from_line_vec(board));
+        let mut board = vec![];
+        for line in lines.chain(std::iter::once("")) {
+            if !line.trim().is_empty() {
+                board.push(line.to_owned());
+            } else if !board.is_empty() {
+                boards.push(Board::from_line_vec(std::mem::take(&mut board))?);
+            }
         }
 
-        Ok(Self { drawing, boards })
+        Ok(Self::new(drawing, boards))
     }
 }
 
 #[derive(Clone)]
-struct Board {
-    data: Vec<u8>,
+struct Board<T = u8> {
+    rows: usize,
+    cols: usize,
+    /// Row by row
+    data: Vec<T>,
     marks: Vec<bool>,
+    /// Where each number is. Numbers are never on a board twice.
+    cells: HashMap<T, usize>,
+    /// Every way this board can win
+    lines: Vec<Line>,
+    /// Which of `lines` each cell is part of
+    cell_lines: Vec<Vec<usize>>,
+    /// How many cells are marked in each line, so a win is just one of these
+    /// getting to the length of its line.
+    line_marks: Vec<usize>,
+    /// The line that won first
+    won: Option<usize>,
 }
 
-impl Board {
-    pub fn drawn(&mut self, num: u8) {
+impl<T: BingoNumber> Board<T> {
+    /// Wins on rows and columns. See [Board::set_patterns] for anything else.
+    ///
+    /// # Panics
+    /// If `data` isn't `rows * cols` long.
+    pub fn new(rows: usize, cols: usize, data: Vec<T>) -> Self {
+        assert_eq!(data.len(), rows * cols, "board data is the wrong size");
+
+        let cells = data
+            .iter()
+            .enumerate()
+            .map(|(idx, num)| (*num, idx))
+            .collect();
+
+        let mut board = Self {
+            rows,
+            cols,
+            marks: vec![false; data.len()],
+            data,
+            cells,
+            lines: vec![],
+            cell_lines: vec![],
+            line_marks: vec![],
+            won: None,
+        };
+        board.set_patterns(&WinPatterns::default());
+
+        board
+    }
+
+    /// Swap out the lines that win. Anything already marked still counts,
+    /// and so does a free centre, which gets marked here.
+    pub fn set_patterns(&mut self, patterns: &WinPatterns) {
+        self.lines = patterns
+            .patterns
+            .iter()
+            .flat_map(|pattern| {
+                pattern
+                    .lines(self.rows, self.cols)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(which, cells)| Line {
+                        pattern: *pattern,
+                        which,
+                        cells,
+                    })
+            })
+            .collect();
+
+        self.cell_lines = vec![vec![]; self.data.len()];
+        for (idx, line) in self.lines.iter().enumerate() {
+            for cell in &line.cells {
+                self.cell_lines[*cell].push(idx);
+            }
+        }
+
+        self.count_line_marks();
+        self.won = self
+            .lines
+            .iter()
+            .zip(&self.line_marks)
+            .position(|(line, marked)| line.cells.len() == *marked);
+
+        if patterns.free_centre && self.rows % 2 == 1 && self.cols % 2 == 1 {
+            self.mark(self.rows / 2 * self.cols + self.cols / 2);
+        }
+    }
+
+    /// Put the marks back how they were, with the line that won then. The
+    /// line can't be worked out from the marks, it's whichever was first.
+    pub fn restore(&mut self, marks: &[bool], won: Option<usize>) {
+        self.marks.clone_from_slice(marks);
+        self.count_line_marks();
+        self.won = won;
+    }
+
+    fn count_line_marks(&mut self) {
+        self.line_marks = self
+            .lines
+            .iter()
+            .map(|line| line.cells.iter().filter(|c| self.marks[**c]).count())
+            .collect();
+    }
+
+    pub fn drawn(&mut self, num: T) {
+        if let Some(cell) = self.cells.get(&num) {
+            self.mark(*cell);
+        }
+    }
+
+    pub fn mark(&mut self, cell: usize) {
+        if self.marks[cell] {
+            return;
+        }
+
+        self.marks[cell] = true;
+        for line in &self.cell_lines[cell] {
+            self.line_marks[*line] += 1;
+
+            if self.won.is_none() && self.line_marks[*line] == self.lines[*line].cells.len() {
+                self.won = Some(*line);
+            }
+        }
+    }
+
+    pub fn check_won(&self) -> bool {
+        self.won.is_some()
+    }
+
+    pub fn winning_line(&self) -> Option<&Line> {
+        self.won.map(|idx| &self.lines[idx])
+    }
+
+    /// How we used to mark: look at every cell. Kept to check and bench the
+    /// indexes against. It doesn't touch the counters.
+    pub fn drawn_scan(&mut self, num: T) {
         for (idx, v) in self.data.iter().enumerate() {
             if *v == num {
                 self.marks[idx] = true;
@@ -119,13 +1060,16 @@ impl Board {
         }
     }
 
-    pub fn check_won(&self) -> bool {
+    /// How we used to check for a win: look at every row and column. It
+    /// doesn't know about any other patterns, so it's only the same as
+    /// [Board::check_won] for the default ones. It's what `--bench` times.
+    pub fn check_won_scan(&self) -> bool {
         // Check rows
-        'row: for row in 0..5 {
-            for col in 0..5 {
-                let idx = row * 5 + col;
+        'row: for row in 0..self.rows {
+            for col in 0..self.cols {
+                let idx = row * self.cols + col;
 
-                if self.marks[idx] == false {
+                if !self.marks[idx] {
                     continue 'row;
                 }
             }
@@ -134,11 +1078,11 @@ impl Board {
         }
 
         // Check cols
-        'col: for col in 0..5 {
-            for row in 0..5 {
-                let idx = row * 5 + col;
+        'col: for col in 0..self.cols {
+            for row in 0..self.rows {
+                let idx = row * self.cols + col;
 
-                if self.marks[idx] == false {
+                if !self.marks[idx] {
                     continue 'col;
                 }
             }
@@ -156,23 +1100,45 @@ impl Board {
      6 10  3 18  5
      1 12 20 15 19
     */
-    pub fn from_line_vec(lines: Vec<String>) -> Board {
-        if lines.len() != 5 {
-            panic!("board drom_line is not 5 data")
-        }
+    /// One line per row. Every row has to be as long as the first.
+    pub fn from_line_vec(lines: Vec<String>) -> Result<Self, String> {
+        let rows = lines.len();
+        let mut cols = None;
 
         let mut data = vec![];
         for line in lines {
-            data.extend(Self::do_line(&line));
+            let nums = Self::do_line(&line)?;
+
+            match cols {
+                None => cols = Some(nums.len()),
+                Some(cols) if cols != nums.len() => {
+                    return Err(format!(
+                        "board row '{}' has {} numbers but the first row had {}",
+                        line.trim(),
+                        nums.len(),
+                        cols
+                    ))
+                }
+                Some(_) => (),
+            }
+
+            data.extend(nums);
         }
 
-        Self {
-            data,
-            marks: vec![false; 25],
+        let cols = match cols {
+            Some(0) | None => return Err("board is empty".into()),
+            Some(cols) => cols,
+        };
+
+        let board = Self::new(rows, cols, data);
+        if board.cells.len() != board.data.len() {
+            return Err("board has a number on it twice".into());
         }
+
+        Ok(board)
     }
 
-    pub fn unmarked(&self) -> Vec<u8> {
+    pub fn unmarked(&self) -> Vec<T> {
         self.data
             .iter()
             .zip(self.marks.iter())
@@ -180,23 +1146,63 @@ impl Board {
             .collect()
     }
 
-    fn do_line(raw: &str) -> Vec<u8> {
-        let mut nums = vec![];
+    pub fn unmarked_sum(&self) -> u64 {
+        self.unmarked().into_iter().map(Into::into).sum()
+    }
 
-        let mut curr = String::new();
-        for (idx, ch) in raw.chars().enumerate() {
-            match idx % 3 {
-                0 => curr.push(ch),
-                1 => {
-                    curr.push(ch);
-                    nums.push(u8::from_str_radix(curr.trim(), 10).unwrap());
-                    curr.clear();
-                }
-                _ => (),
-            }
+    /// One line per row. In colour marked numbers are green and the line
+    /// the board won with is yellow and inverted. Without colour marked
+    /// numbers are in `[brackets]` and the winning line is in `*stars*`.
+    pub fn render(&self, colour: bool) -> Vec<String> {
+        let width = self.data.iter().map(|n| n.to_string().len()).max();
+        let width = width.unwrap_or(0);
+        let winning = self.winning_line().map(|l| &l.cells[..]).unwrap_or(&[]);
+
+        (0..self.rows)
+            .map(|row| {
+                let cells: Vec<String> = (row * self.cols..(row + 1) * self.cols)
+                    .map(|cell| {
+                        let num = format!("{:>width$}", self.data[cell], width = width);
+                        let won = winning.contains(&cell);
+
+                        let styles: &[Style] = match (won, self.marks[cell]) {
+                            (true, _) => &[Style::Bold, Style::Yellow, Style::Reverse],
+                            (false, true) => &[Style::Bold, Style::Green],
+                            (false, false) => &[Style::Dim],
+                        };
+
+                        match (colour, won, self.marks[cell]) {
+                            (true, ..) => format!(" {} ", term::paint(&num, styles, true)),
+                            (false, true, _) => format!("*{}*", num),
+                            (false, false, true) => format!("[{}]", num),
+                            (false, false, false) => format!(" {} ", num),
+                        }
+                    })
+                    .collect();
+
+                cells.join("").trim_end().to_owned()
+            })
+            .collect()
+    }
+
+    fn do_line(raw: &str) -> Result<Vec<T>, String> {
+        raw.split_whitespace()
+            .map(|s| {
+                s.parse()
+                    .map_err(|_| format!("'{}' isn't a number for a board", s))
+            })
+            .collect()
+    }
+}
+
+/// The rows without colour, see [Board::render]
+impl<T: BingoNumber> fmt::Display for Board<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.render(false) {
+            writeln!(f, "{}", row)?;
         }
 
-        nums
+        Ok(())
     }
 }
 
@@ -204,8 +1210,19 @@ impl Board {
 mod test {
     use std::fs::read_to_string;
 
+    use aoc2021::{rng::Rng, simulation::Runner};
+
     use super::*;
 
+    /// [Board::check_won_scan] for any patterns: look at every cell of every
+    /// line.
+    fn lines_scan_won<T: BingoNumber>(board: &Board<T>) -> bool {
+        board
+            .lines
+            .iter()
+            .any(|line| line.cells.iter().all(|cell| board.marks[*cell]))
+    }
+
     fn test_board() -> String {
         [
             "22 13 17 11  0",
@@ -227,13 +1244,13 @@ mod test {
         let line = test_board().lines().next().unwrap().to_owned();
         let expected = vec![22, 13, 17, 11, 0];
 
-        assert_eq!(expected, Board::do_line(&line))
+        assert_eq!(Ok(expected), Board::<u8>::do_line(&line))
     }
 
     #[test]
     fn board_fails_to_find_win() {
         let lines = test_board().lines().map(|l| l.to_owned()).collect();
-        let board = Board::from_line_vec(lines);
+        let board: Board = Board::from_line_vec(lines).unwrap();
 
         let mut row = board.clone();
         row.drawn(22);
@@ -255,18 +1272,485 @@ mod test {
     #[test]
     fn test_first_win_fails() {
         let mut game = day_test();
-        let (board, last) = game.draw_until_winner();
+        let win = game.draw_until_winner().unwrap();
 
-        assert_eq!(board.data[0], 14);
-        assert_eq!(last, 24);
+        assert_eq!(game.boards[win.board].data[0], 14);
+        assert_eq!(win.number, 24);
+        assert_eq!(win.score(false), "4512");
     }
 
     #[test]
     fn test_last_win_fails() {
         let mut game = day_test();
-        let (board, last) = game.find_last_win();
+        let win = game.find_last_win().unwrap();
+
+        assert_eq!(game.boards[win.board].data[0], 3);
+        assert_eq!(win.number, 13);
+        assert_eq!(win.score(false), "1924");
+    }
+
+    #[test]
+    fn finished_game_has_wrong_cycle() {
+        let game = day_test();
+        let draws = game.drawing.len();
+        let mut runner = Runner::new(game);
+
+        // Nothing changes after the last draw, so that's a cycle of one
+        let cycle = runner.find_cycle(100);
+        assert_eq!(cycle.map(|c| (c.start, c.length)), Some((draws, 1)));
+
+        runner.fast_forward(1_000_000_000, 100);
+        assert_eq!(runner.steps(), 1_000_000_000);
+        assert_eq!(runner.sim().drawn, draws);
+    }
+
+    #[test]
+    fn indexed_wins_disagree_with_scanning() {
+        let mut rng = Rng::new(36);
+        let mut numbers: Vec<u8> = (0..100).collect();
+
+        for _ in 0..200 {
+            rng.shuffle(&mut numbers);
+            let mut indexed = Board::new(5, 5, numbers[..25].to_vec());
+            let mut scanned = indexed.clone();
+
+            rng.shuffle(&mut numbers);
+            for draw in &numbers {
+                indexed.drawn(*draw);
+                scanned.drawn_scan(*draw);
+
+                assert_eq!(indexed.marks, scanned.marks);
+                assert_eq!(indexed.check_won(), scanned.check_won_scan());
+                assert_eq!(indexed.check_won(), lines_scan_won(&scanned));
+            }
+        }
+    }
+
+    #[test]
+    fn game_index_is_wrong() {
+        let game = day_test();
+
+        // 24 is in every test board once
+        let mut places = game.index[&24].clone();
+        places.sort();
+        assert_eq!(places, vec![(0, 9), (1, 18), (2, 3)]);
+        for (board, cell) in places {
+            assert_eq!(game.boards[board].data[cell], 24);
+        }
+    }
+
+    #[test]
+    fn board_fails_to_parse_wide_numbers() {
+        let nums = Board::<u16>::do_line("  100 2\t30000   7 ");
+        assert_eq!(nums, Ok(vec![100, 2, 30000, 7]));
+
+        assert!(Board::<u8>::do_line("1 256").is_err());
+        assert!(Board::<u8>::do_line("1 two").is_err());
+    }
+
+    #[test]
+    fn bad_boards_parse() {
+        let ragged = vec!["1 2 3".to_owned(), "4 5".to_owned()];
+        assert!(Board::<u8>::from_line_vec(ragged).is_err());
+
+        let twice = vec!["1 2".to_owned(), "2 3".to_owned()];
+        assert!(Board::<u8>::from_line_vec(twice).is_err());
+
+        assert!("1,2,x\n\n1 2\n3 4".parse::<BingoGame>().is_err());
+    }
+
+    #[test]
+    fn small_game_is_wrong() {
+        // Two 3x3 boards and a 2x4, with more than one blank line between
+        // some of them and ragged spacing within
+        let input = "5, 1, 9, 3, 7, 2\n\n1 2 3\n4 5 6\n7  8  9\n\n\n\
+            9 8 7\n 6 5 4\n3 2 1\n\n\t1 3 5 7\n2 4 6 8\n";
+        let mut game: BingoGame = input.parse().unwrap();
 
-        assert_eq!(board.data[0], 3);
-        assert_eq!(last, 13);
+        let shapes: Vec<_> = game.boards.iter().map(|b| (b.rows, b.cols)).collect();
+        assert_eq!(shapes, vec![(3, 3), (3, 3), (2, 4)]);
+
+        // 5, 1, 9, 3, 7 are the corners and centre of the 3x3s, which
+        // aren't lines, but they're the whole top row of the 2x4
+        let win = game.clone().draw_until_winner().unwrap();
+        assert_eq!((win.board, win.number), (2, 7));
+        assert_eq!(win.unmarked, 2 + 4 + 6 + 8);
+
+        // 2 finishes a row on both 3x3s at once, the first is taken
+        let win = game.find_last_win().unwrap();
+        assert_eq!((win.board, win.number), (0, 2));
+        assert_eq!(game.boards[win.board].data[0], 1);
+        assert_eq!(win.unmarked, 4 + 6 + 8);
+    }
+
+    #[test]
+    fn big_game_is_wrong() {
+        // A 10x10 of u32s too wide for the old fixed width columns
+        let mut input = String::new();
+        let drawing: Vec<String> = (0..10)
+            .map(|row| (row * 10 + 3) * 1000)
+            .map(|n| n.to_string())
+            .collect();
+        input.push_str(&drawing.join(","));
+        input.push_str("\n\n");
+        for row in 0..10u32 {
+            let line: Vec<String> = (0..10)
+                .map(|col| ((row * 10 + col) * 1000).to_string())
+                .collect();
+            input.push_str(&line.join(" "));
+            input.push('\n');
+        }
+
+        let mut game: BingoGame<u32> = input.parse().unwrap();
+        let win = game.draw_until_winner().unwrap();
+
+        assert_eq!((game.boards[0].rows, game.boards[0].cols), (10, 10));
+        assert_eq!((win.draw, win.number), (9, 93_000));
+        assert_eq!(win.line.to_string(), "column 3");
+        assert_eq!(game.drawn, 10);
+
+        let total: u64 = (0..100u64).map(|n| n * 1000).sum();
+        let column: u64 = (0..10u64).map(|row| (row * 10 + 3) * 1000).sum();
+        assert_eq!(win.unmarked, total - column);
+        assert_eq!(win.score(false), win.score(true));
+    }
+
+    #[test]
+    fn test_game_shape_is_wrong() {
+        let game = day_test();
+
+        assert_eq!(game.boards.len(), 3);
+        for board in &game.boards {
+            assert_eq!((board.rows, board.cols), (5, 5));
+        }
+    }
+
+    #[test]
+    fn patterns_fail_to_parse() {
+        let patterns: WinPatterns = "rows, x,free".parse().unwrap();
+        assert_eq!(patterns.patterns, vec![WinPattern::Rows, WinPattern::X]);
+        assert!(patterns.free_centre);
+
+        assert!("free".parse::<WinPatterns>().is_err());
+        assert!("rows,squares".parse::<WinPatterns>().is_err());
+    }
+
+    #[test]
+    fn pattern_lines_are_wrong() {
+        let count = |p: WinPattern, rows, cols| p.lines(rows, cols).len();
+        assert_eq!(count(WinPattern::Rows, 4, 3), 4);
+        assert_eq!(count(WinPattern::Columns, 4, 3), 3);
+        assert_eq!(count(WinPattern::Diagonals, 4, 3), 0);
+        assert_eq!(count(WinPattern::X, 4, 3), 0);
+
+        assert_eq!(
+            WinPattern::Diagonals.lines(3, 3),
+            vec![vec![0, 4, 8], vec![2, 4, 6]]
+        );
+        assert_eq!(WinPattern::X.lines(3, 3), vec![vec![0, 2, 4, 6, 8]]);
+        assert_eq!(WinPattern::Corners.lines(4, 3), vec![vec![0, 2, 9, 11]]);
+        assert_eq!(WinPattern::Corners.lines(1, 1), vec![vec![0]]);
+        assert_eq!(WinPattern::Blackout.lines(2, 2), vec![vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn board_fails_to_win_on_pattern() {
+        let lines = test_board().lines().map(|l| l.to_owned()).collect();
+        let board: Board = Board::from_line_vec(lines).unwrap();
+        let diagonal = [22, 2, 14, 18, 19];
+
+        let mut plain = board.clone();
+        diagonal.iter().for_each(|n| plain.drawn(*n));
+        assert!(!plain.check_won());
+
+        let mut diagonals = board.clone();
+        diagonals.set_patterns(&"diagonals".parse().unwrap());
+        diagonal.iter().for_each(|n| diagonals.drawn(*n));
+        let line = diagonals.winning_line().unwrap();
+        assert_eq!(line.to_string(), "top-left diagonal");
+
+        let mut corners = board.clone();
+        corners.set_patterns(&"rows,corners".parse().unwrap());
+        [22, 0, 1].iter().for_each(|n| corners.drawn(*n));
+        assert!(!corners.check_won());
+        corners.drawn(19);
+        assert_eq!(corners.winning_line().unwrap().to_string(), "four corners");
+
+        let mut blackout = board;
+        blackout.set_patterns(&"blackout".parse().unwrap());
+        for n in 0..25 {
+            assert!(!blackout.check_won());
+            blackout.drawn(n);
+        }
+        assert_eq!(blackout.winning_line().unwrap().to_string(), "blackout");
+    }
+
+    #[test]
+    fn free_centre_is_wrong() {
+        let lines = test_board().lines().map(|l| l.to_owned()).collect();
+        let mut board: Board = Board::from_line_vec(lines).unwrap();
+        board.set_patterns(&"rows,columns,free".parse().unwrap());
+
+        // 14 is in the middle and comes for free
+        assert!(board.marks[12]);
+        assert_eq!(board.unmarked_sum(), (0..=24).sum::<u64>() - 14);
+
+        [21, 9, 16].iter().for_each(|n| board.drawn(*n));
+        assert!(!board.check_won());
+        board.drawn(7);
+        assert_eq!(board.winning_line().unwrap().to_string(), "row 2");
+
+        // Even boards don't have a middle
+        let mut even: Board = Board::new(2, 2, vec![1, 2, 3, 4]);
+        even.set_patterns(&"rows,free".parse().unwrap());
+        assert!(even.marks.iter().all(|m| !m));
+    }
+
+    #[test]
+    fn small_game_pattern_is_wrong() {
+        let input = "5,1,9,7,3,2\n\n1 2 3\n4 5 6\n7 8 9\n\n3 8 7\n6 5 4\n9 2 1\n";
+        let game: BingoGame = input.parse().unwrap();
+        let mut game = game.with_patterns(&"rows,columns,diagonals".parse().unwrap());
+
+        // The first board has 1 5 9 going down, the second has 7 5 9 going
+        // up, so it needs one more
+        let win = game.clone().draw_until_winner().unwrap();
+        assert_eq!((win.board, win.number), (0, 9));
+        assert_eq!(win.line.to_string(), "top-left diagonal");
+
+        let win = game.find_last_win().unwrap();
+        assert_eq!((win.board, win.number), (1, 7));
+        assert_eq!(win.line.to_string(), "top-right diagonal");
+    }
+
+    #[test]
+    fn pattern_wins_disagree_with_scanning() {
+        let mut rng = Rng::new(38);
+        let mut numbers: Vec<u8> = (0..100).collect();
+        let patterns = [
+            "rows,columns,free",
+            "diagonals",
+            "corners,x",
+            "blackout",
+            "rows,columns,diagonals,corners,x,blackout,free",
+        ];
+
+        for trial in 0..200 {
+            let (rows, cols) = (1 + rng.below(6) as usize, 1 + rng.below(6) as usize);
+            rng.shuffle(&mut numbers);
+            let mut indexed = Board::new(rows, cols, numbers[..rows * cols].to_vec());
+            indexed.set_patterns(&patterns[trial % patterns.len()].parse().unwrap());
+            let mut scanned = indexed.clone();
+
+            rng.shuffle(&mut numbers);
+            for draw in &numbers {
+                indexed.drawn(*draw);
+                scanned.drawn_scan(*draw);
+
+                assert_eq!(indexed.marks, scanned.marks);
+                assert_eq!(indexed.check_won(), lines_scan_won(&scanned));
+            }
+        }
+    }
+
+    #[test]
+    fn timeline_is_wrong() {
+        let mut game = day_test();
+        let timeline = game.clone().timeline();
+
+        let order: Vec<_> = timeline.iter().map(|w| (w.board, w.number)).collect();
+        assert_eq!(order, vec![(2, 24), (0, 16), (1, 13)]);
+        assert!(timeline.windows(2).all(|w| w[0].draw <= w[1].draw));
+
+        for (k, win) in timeline.iter().enumerate() {
+            assert_eq!(game.clone().winner(k).as_ref(), Some(win));
+        }
+        assert_eq!(game.winner(3), None);
+    }
+
+    #[test]
+    fn timeline_disagrees_with_replaying() {
+        let mut rng = Rng::new(39);
+        let mut numbers: Vec<u8> = (0..100).collect();
+
+        for _ in 0..50 {
+            let mut boards = vec![];
+            for _ in 0..1 + rng.below(10) {
+                rng.shuffle(&mut numbers);
+                boards.push(Board::new(5, 5, numbers[..25].to_vec()));
+            }
+            rng.shuffle(&mut numbers);
+            let game = BingoGame::new(numbers.clone(), boards);
+
+            // Every board ends up with every number drawn, so they all win
+            let timeline = game.clone().timeline();
+            assert_eq!(timeline.len(), game.boards.len());
+
+            for win in timeline {
+                let mut board = game.boards[win.board].clone();
+                for draw in &game.drawing[..win.draw] {
+                    board.drawn(*draw);
+                }
+                assert!(!board.check_won());
+
+                board.drawn(win.number);
+                assert!(board.check_won());
+                assert_eq!(board.unmarked_sum(), win.unmarked);
+                assert_eq!(board.winning_line(), Some(&win.line));
+            }
+        }
+    }
+
+    #[test]
+    fn monte_carlo_depends_on_threads() {
+        let game = day_test();
+
+        let one = game.monte_carlo(300, 1, 40);
+        assert_eq!(one, game.monte_carlo(300, 3, 40));
+        assert_eq!(one, game.monte_carlo(300, 8, 40));
+        assert_ne!(one, game.monte_carlo(300, 1, 41));
+    }
+
+    #[test]
+    fn monte_carlo_counts_are_wrong() {
+        let game = day_test();
+        let stats = game.monte_carlo(500, 4, 40);
+
+        // Every test board wins once everything's drawn, so somebody always
+        // wins first and somebody always wins last
+        assert_eq!(stats.trials, 500);
+        assert_eq!(stats.first.iter().sum::<u64>(), 500);
+        assert_eq!(stats.last.iter().sum::<u64>(), 500);
+        assert_eq!(stats.wins, vec![500; 3]);
+        assert_eq!(stats.total_histogram().iter().sum::<u64>(), 1500);
+
+        // Nobody can win before their fifth number
+        assert!(stats
+            .histogram
+            .iter()
+            .all(|h| h[..4].iter().all(|c| *c == 0)));
+    }
+
+    #[test]
+    fn monte_carlo_odds_are_wrong() {
+        // A one cell board wins on whichever draw its number comes out, so
+        // that's even across all five
+        let game: BingoGame = "1,2,3,4,5\n\n5".parse().unwrap();
+        let stats = game.monte_carlo(5000, 2, 40);
+
+        assert_eq!((stats.p_first(0), stats.p_last(0)), (1.0, 1.0));
+        assert!((stats.mean_draw(0).unwrap() - 2.0).abs() < 0.1);
+        for count in &stats.histogram[0] {
+            assert!((900..1100).contains(count), "{:?}", stats.histogram);
+        }
+
+        // And a board that can't win never does
+        let game: BingoGame = "1,2\n\n5".parse().unwrap();
+        let stats = game.monte_carlo(10, 2, 40);
+        assert_eq!(stats.mean_draw(0), None);
+        assert_eq!(stats.first, vec![0]);
+    }
+
+    #[test]
+    fn game_events_are_wrong() {
+        let mut game: BingoGame = "2,1,4\n\n1 2\n3 4\n".parse().unwrap();
+        let events = events::capture(|| {
+            game.draw_until_winner();
+        });
+
+        // seq counts across every test, so only from the event on is ours
+        let events: Vec<_> = events
+            .iter()
+            .map(|e| {
+                assert!(e.starts_with(r#"{"day":"day4","seq":"#), "{}", e);
+                &e[e.find(r#""event""#).unwrap()..]
+            })
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                r#""event":"draw","draw":0,"number":2}"#,
+                r#""event":"marked","board":0,"row":0,"col":1}"#,
+                r#""event":"draw","draw":1,"number":1}"#,
+                r#""event":"marked","board":0,"row":0,"col":0}"#,
+                r#""event":"won","board":0,"draw":1,"number":1,"line":"row 0","score":7}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn debugger_goes_back_wrong() {
+        let game: BingoGame = "2,1,4\n\n1 2\n3 4\n\n4 2\n3 5\n".parse().unwrap();
+        let script =
+            "until any won\nback\nnext\nshow\nuntil all won\nuntil board 1 won\nuntil drawn 9\ngoto 1\nnext 5\nquit\n";
+        let mut out = vec![];
+        debugger::run(game, script.as_bytes(), &mut out, 40, false).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[0] nothing drawn yet, no boards won\n\
+             [0]> [2] draw 1 was 1, 1 board won\n\
+             [2]> [1] draw 0 was 2, no boards won\n\
+             [1]> [2] draw 1 was 1, 1 board won\n\
+             [2]> board 0, won with row 0   board 1\n\
+             *1**2*                     4 [2]\n \
+             3  4                      3  5\n\
+             [2]> [3] draw 2 was 4, 2 boards won\n\
+             [3]> [3] draw 2 was 4, 2 boards won\n\
+             [3]> finished without that happening\n\
+             [3] draw 2 was 4, 2 boards won\n\
+             [3]> [1] draw 0 was 2, no boards won\n\
+             [1]> finished, there's nothing after step 3\n\
+             [3] draw 2 was 4, 2 boards won\n\
+             [3]> "
+        );
+    }
+
+    #[test]
+    fn boards_render_wrong() {
+        let input = "2,1,4\n\n1 2\n3 4\n\n4 2\n3 5\n\n10 11\n12 13\n";
+        let mut game: BingoGame = input.parse().unwrap();
+        game.draw_until_winner();
+
+        assert_eq!(game.boards[0].to_string(), "*1**2*\n 3  4\n");
+        assert_eq!(game.boards[1].to_string(), " 4 [2]\n 3  5\n");
+
+        // The third board doesn't fit in 40 columns so it gets its own row,
+        // and its numbers are wider
+        assert_eq!(
+            game.render(40, false),
+            "board 0, won with row 0   board 1\n\
+             *1**2*                     4 [2]\n \
+             3  4                      3  5\n\
+             \n\
+             board 2\n \
+             10  11\n \
+             12  13\n"
+        );
+
+        // Colour doesn't move any numbers, it just swaps the brackets and
+        // stars for escape codes
+        let coloured = game.render(40, true);
+        assert!(coloured.contains("\x1b[1;33;7m1\x1b[0m"));
+        assert!(coloured.contains("\x1b[1;32m2\x1b[0m"));
+
+        let unescaped: Vec<String> = coloured
+            .split('\x1b')
+            .enumerate()
+            .map(|(idx, part)| match idx {
+                0 => part,
+                _ => &part[part.find('m').unwrap() + 1..],
+            })
+            .collect::<String>()
+            .lines()
+            .map(|l| l.trim_end().to_owned())
+            .collect();
+        let unmarked: Vec<String> = game
+            .render(40, false)
+            .replace(['[', ']', '*'], " ")
+            .lines()
+            .map(|l| l.trim_end().to_owned())
+            .collect();
+        assert_eq!(unescaped, unmarked);
     }
 }
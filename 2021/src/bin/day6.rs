@@ -1,55 +1,671 @@
-use std::{str::FromStr, time::Instant};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    str::FromStr,
+    time::Instant,
+};
 
-use aoc2021::day_parse;
+use aoc2021::{
+    arg_flag, arg_value,
+    bigint::BigUint,
+    day_parse,
+    debugger::{self, Condition, Inspect},
+    error, event,
+    events::{self, Event, Value},
+    info,
+    simulation::{Runner, Simulation},
+    term, trace,
+};
 
 fn main() {
+    let _events = events::FlushGuard;
     let mut world: LanternWorld = day_parse!();
+    if let Some(model) = arg_value::<LanternModel>("--model").unwrap() {
+        world = world.with_model(model).unwrap();
+    }
+    let start = world.clone();
+    let exact = arg_flag("--exact");
 
-    let before = Instant::now();
-    for _ in 0..80 {
-        world.tick();
+    if arg_flag("--debug") {
+        let stdin = io::stdin();
+        debugger::run(
+            world,
+            stdin.lock(),
+            io::stdout(),
+            term::width(),
+            term::stdout_colour(),
+        )
+        .unwrap();
+        return;
     }
+
+    if let Some(path) = arg_value::<String>("--histogram").unwrap() {
+        let days = arg_value("--days").unwrap().unwrap_or(256);
+        let written =
+            File::create(&path).and_then(|file| world.write_histogram(days, BufWriter::new(file)));
+        match written {
+            Ok(()) => info!("Wrote {} days of timer histograms to {}", days, path),
+            Err(e) => {
+                error!("Couldn't write the histograms to {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut runner = Runner::new(world);
+
+    let before = Instant::now();
+    runner.step_n(80);
     let time_80 = before.elapsed();
 
     println!(
         "Fish after 80 days {} [{} nanosec]",
-        world.count(),
+        runner.sim().count_string(exact),
         time_80.as_nanos()
     );
 
     let before = Instant::now();
-    for _ in 0..(256 - 80) {
-        world.tick();
-    }
+    runner.step_n(256 - 80);
     let time_total = time_80 + before.elapsed();
 
     println!(
         "Fish after 256 days {} [{} nanosec]",
-        world.count(),
+        runner.sim().count_string(exact),
         time_total.as_nanos()
     );
+
+    if let Some(day) = arg_value::<u64>("--day").unwrap() {
+        let before = Instant::now();
+        let count = match start.count_on_day(day) {
+            Some(count) if !exact => count.to_string(),
+            _ => start.count_on_day_big(day).to_string(),
+        };
+
+        println!(
+            "Fish after {} days {} [{} nanosec]",
+            day,
+            count,
+            before.elapsed().as_nanos()
+        );
+    }
 }
 
+/// The rules the fish live by. The puzzle's fish are the [Default]: timers
+/// reset to six, babies start at eight, one baby each time a timer hits zero,
+/// and they live forever. Parses from comma separated `key=value` pairs,
+/// like `reset=6,newborn=8,lifespan=40,spawn=0:1`. Every `spawn=timer:count`
+/// is how many babies a fish with that timer has each day; give any and
+/// they replace the default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LanternModel {
+    reset: usize,
+    newborn: usize,
+    /// Fish die once they've been alive this many days. We don't know how
+    /// old the fish in the input are, so they start at zero days.
+    lifespan: Option<usize>,
+    /// `spawns[timer]` is how many babies a fish with that timer has a day.
+    spawns: Vec<usize>,
+}
+
+impl LanternModel {
+    /// How many different timer values there are.
+    pub fn timers(&self) -> usize {
+        self.reset.max(self.newborn).max(self.spawns.len() - 1) + 1
+    }
+
+    /// How many different ages we have to keep track of. One if the fish
+    /// live forever, because then we don't track age at all.
+    pub fn ages(&self) -> usize {
+        self.lifespan.unwrap_or(1)
+    }
+
+    /// How many fish one fish with `timer` has each day.
+    fn spawn(&self, timer: usize) -> usize {
+        self.spawns.get(timer).copied().unwrap_or(0)
+    }
+
+    /// Where the fish in bucket `from` go in one tick and how many they turn
+    /// into there, for every bucket. Every tick, and every column of the
+    /// transition matrix, is built from this.
+    fn transitions(&self) -> Vec<Vec<(usize, usize)>> {
+        let timers = self.timers();
+        (0..timers * self.ages())
+            .map(|from| self.transitions_from(from, timers))
+            .collect()
+    }
+
+    fn transitions_from(&self, from: usize, timers: usize) -> Vec<(usize, usize)> {
+        let (age, timer) = (from / timers, from % timers);
+
+        let mut to = vec![];
+        let age = match self.lifespan {
+            None => 0,
+            Some(lifespan) if age + 1 < lifespan => age + 1,
+            // Too old. No more fish, and no more babies either
+            Some(_) => return to,
+        };
+
+        let next_timer = if timer == 0 { self.reset } else { timer - 1 };
+        to.push((age * timers + next_timer, 1));
+
+        let babies = self.spawn(timer);
+        if babies > 0 {
+            to.push((self.newborn, babies));
+        }
+
+        to
+    }
+}
+
+impl Default for LanternModel {
+    fn default() -> Self {
+        Self {
+            reset: 6,
+            newborn: 8,
+            lifespan: None,
+            spawns: vec![1],
+        }
+    }
+}
+
+impl FromStr for LanternModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut model = Self::default();
+        let mut spawns = vec![];
+
+        for pair in s.split(',') {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("'{}' is not key=value", pair))?;
+            let number = |v: &str| {
+                v.parse::<usize>()
+                    .map_err(|e| format!("Failed to parse '{}' for {}: {}", v, key, e))
+            };
+
+            match key {
+                "reset" => model.reset = number(value)?,
+                "newborn" => model.newborn = number(value)?,
+                "lifespan" => match number(value)? {
+                    0 => return Err("a lifespan of zero means there are no fish".into()),
+                    days => model.lifespan = Some(days),
+                },
+                "spawn" => {
+                    let (timer, count) = value
+                        .split_once(':')
+                        .ok_or_else(|| format!("spawn '{}' is not timer:count", value))?;
+                    spawns.push((number(timer)?, number(count)?));
+                }
+                _ => return Err(format!("'{}' is not part of a lantern model", key)),
+            }
+        }
+
+        if !spawns.is_empty() {
+            let len = spawns.iter().map(|(timer, _)| timer + 1).max().unwrap();
+            model.spawns = vec![0; len];
+            for (timer, count) in spawns {
+                model.spawns[timer] += count;
+            }
+        }
+
+        Ok(model)
+    }
+}
+
+#[derive(Clone)]
 struct LanternWorld {
-    fish: [usize; 9],
+    model: LanternModel,
+    /// How many fish have each timer and age, indexed by
+    /// `age * model.timers() + timer`. Age is always zero when the model
+    /// doesn't have a lifespan.
+    fish: Vec<usize>,
+    /// How many times we've ticked
+    day: usize,
+    /// [LanternModel::transitions], worked out once
+    transitions: Vec<Vec<(usize, usize)>>,
 }
 
 impl LanternWorld {
+    /// `fish[timer]` is how many fish start with that timer, all at age zero.
+    pub fn new(model: LanternModel, fish: &[usize]) -> Result<Self, String> {
+        let timers = model.timers();
+        if fish.len() > timers {
+            if let Some(timer) = (timers..fish.len()).find(|timer| fish[*timer] != 0) {
+                return Err(format!(
+                    "there's a fish with timer {} but this model's timers only go to {}",
+                    timer,
+                    timers - 1
+                ));
+            }
+        }
+
+        let mut buckets = vec![0; timers * model.ages()];
+        for (timer, count) in fish.iter().enumerate().take(timers) {
+            buckets[timer] = *count;
+        }
+
+        Ok(Self {
+            transitions: model.transitions(),
+            model,
+            fish: buckets,
+            day: 0,
+        })
+    }
+
+    /// The same fish by timer, living by different rules.
+    pub fn with_model(&self, model: LanternModel) -> Result<Self, String> {
+        Self::new(model, &self.histogram())
+    }
+
+    /// # Panics
+    /// If a bucket would have more fish than a usize holds, which is
+    /// somewhere past day 440 for the puzzle. [LanternWorld::count_on_day_big]
+    /// can go as far as you like.
     pub fn tick(&mut self) {
-        let tmp = self.fish[0];
-        self.fish[0] = self.fish[1];
-        self.fish[1] = self.fish[2];
-        self.fish[2] = self.fish[3];
-        self.fish[3] = self.fish[4];
-        self.fish[4] = self.fish[5];
-        self.fish[5] = self.fish[6];
-        self.fish[6] = self.fish[7] + tmp;
-        self.fish[7] = self.fish[8];
-        self.fish[8] = tmp;
+        self.fish = self.next_fish().unwrap_or_else(|| {
+            panic!(
+                "Too many fish to tick past day {}, project with --day and --exact instead",
+                self.day
+            )
+        });
+        self.day += 1;
+        trace!("ticked, {} fish", self.count_string(true));
+        event!(LanternEvent::Tick {
+            days: self.day,
+            population: self.count_big(),
+        });
+    }
+
+    /// The buckets after one tick, or None if any of them would overflow.
+    fn next_fish(&self) -> Option<Vec<usize>> {
+        let mut next = vec![0usize; self.fish.len()];
+        for (count, column) in self.fish.iter().zip(&self.transitions) {
+            for (to, multiplier) in column {
+                next[*to] = next[*to].checked_add(count.checked_mul(*multiplier)?)?;
+            }
+        }
+
+        Some(next)
+    }
+
+    /// None if there are more fish than a usize holds, which can happen
+    /// before any one bucket is full. [LanternWorld::count_big] always works.
+    pub fn count(&self) -> Option<usize> {
+        self.fish
+            .iter()
+            .try_fold(0usize, |sum, fish| sum.checked_add(*fish))
+    }
+
+    /// The count as a [BigUint], so summing the buckets can't overflow even
+    /// when they're all close to full.
+    pub fn count_big(&self) -> BigUint {
+        self.fish
+            .iter()
+            .fold(BigUint::zero(), |sum, fish| sum + BigUint::from(*fish))
+    }
+
+    fn count_string(&self, exact: bool) -> String {
+        match self.count() {
+            Some(count) if !exact => count.to_string(),
+            _ => self.count_big().to_string(),
+        }
+    }
+
+    /// How many fish have each timer, whatever their age.
+    pub fn histogram(&self) -> Vec<usize> {
+        let timers = self.model.timers();
+        let mut histogram = vec![0; timers];
+        for (idx, count) in self.fish.iter().enumerate() {
+            histogram[idx % timers] += count;
+        }
+
+        histogram
+    }
+
+    /// Write the timer histogram of every day for `days` days, including
+    /// today as day zero, as CSV. The buckets are [BigUint]s rather than
+    /// ticking a copy of the world, so it doesn't matter if they'd overflow.
+    pub fn write_histogram<W: Write>(&self, days: usize, mut out: W) -> io::Result<()> {
+        let timers = self.model.timers();
+        let names: Vec<String> = (0..timers).map(|t| t.to_string()).collect();
+        writeln!(out, "day,{},total", names.join(","))?;
+
+        let mut fish: Vec<BigUint> = self.fish.iter().map(|f| BigUint::from(*f)).collect();
+        for day in 0..=days {
+            let mut histogram = vec![BigUint::zero(); timers];
+            for (idx, count) in fish.iter().enumerate() {
+                histogram[idx % timers] = &histogram[idx % timers] + count;
+            }
+            let total = histogram.iter().fold(BigUint::zero(), |sum, c| &sum + c);
+            let histogram: Vec<String> = histogram.iter().map(|c| c.to_string()).collect();
+            writeln!(out, "{},{},{}", day, histogram.join(","), total)?;
+
+            let mut next = vec![BigUint::zero(); fish.len()];
+            for (count, column) in fish.iter().zip(&self.transitions) {
+                for (to, multiplier) in column {
+                    next[*to] = &next[*to] + &(count * &BigUint::from(*multiplier));
+                }
+            }
+            fish = next;
+        }
+
+        Ok(())
+    }
+
+    /// One tick as a matrix. `transition()[to][from]` is how many fish in
+    /// bucket `to` one fish in bucket `from` turns into. For the puzzle's
+    /// model the buckets are the timers and this is 9×9.
+    pub fn transition(&self) -> Vec<Vec<usize>> {
+        let size = self.fish.len();
+
+        let mut matrix = vec![vec![0; size]; size];
+        for (from, column) in self.transitions.iter().enumerate() {
+            for (to, multiplier) in column {
+                matrix[*to][from] += multiplier;
+            }
+        }
+
+        matrix
+    }
+
+    /// How many fish there are `days` ticks after now. The transition matrix
+    /// is raised to `days` by squaring so this is O(log days), unless the
+    /// model has so many buckets that just ticking is cheaper. None if it
+    /// doesn't fit in a u128, which happens somewhere past day 1000 for the
+    /// puzzle.
+    pub fn count_on_day(&self, days: u64) -> Option<u128> {
+        self.project::<u128>(days)
+    }
+
+    /// [LanternWorld::count_on_day] that can't overflow.
+    pub fn count_on_day_big(&self, days: u64) -> BigUint {
+        self.project::<BigUint>(days).unwrap()
+    }
+
+    fn project<T: Element>(&self, days: u64) -> Option<T> {
+        if ticking_is_cheaper(self.fish.len(), days) {
+            self.project_ticking(days)
+        } else {
+            self.project_matrix(days)
+        }
+    }
+
+    fn project_matrix<T: Element>(&self, days: u64) -> Option<T> {
+        let matrix: Matrix<T> = Matrix::from_small(&self.transition()).pow(days)?;
+
+        let mut count = T::zero();
+        for row in matrix.rows() {
+            for (from, cell) in row.iter().enumerate() {
+                let fish = T::from_usize(self.fish[from]);
+                count = count.checked_add(&cell.checked_mul(&fish)?)?;
+            }
+        }
+
+        Some(count)
+    }
+
+    /// [LanternWorld::tick] on a copy of the buckets made of `T`.
+    fn project_ticking<T: Element>(&self, days: u64) -> Option<T> {
+        let mut fish: Vec<T> = self.fish.iter().map(|c| T::from_usize(*c)).collect();
+
+        for _ in 0..days {
+            let mut next = vec![T::zero(); fish.len()];
+            for (count, column) in fish.iter().zip(&self.transitions) {
+                for (to, multiplier) in column {
+                    let babies = count.checked_mul(&T::from_usize(*multiplier))?;
+                    next[*to] = next[*to].checked_add(&babies)?;
+                }
+            }
+            fish = next;
+        }
+
+        fish.iter()
+            .try_fold(T::zero(), |count, bucket| count.checked_add(bucket))
+    }
+}
+
+/// A matrix multiply is buckets³ and we do two per bit of days. A tick is at
+/// most two transitions per bucket. A matrix so big its cost overflows is
+/// never cheaper.
+fn ticking_is_cheaper(buckets: usize, days: u64) -> bool {
+    let size = buckets as u64;
+    let matrix_cost = size
+        .checked_pow(3)
+        .and_then(|cube| cube.checked_mul(2 * (u64::BITS - days.leading_zeros()) as u64));
+    let tick_cost = days.saturating_mul(size.saturating_mul(2));
+
+    matrix_cost.is_none_or(|matrix_cost| tick_cost < matrix_cost)
+}
+
+/// What happened to the fish, for the events file.
+enum LanternEvent {
+    /// The fish after `days` ticks. Not `day`, that's which day of the
+    /// calendar it came from.
+    Tick { days: usize, population: BigUint },
+}
+
+impl Event for LanternEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            LanternEvent::Tick { .. } => "tick",
+        }
+    }
+
+    fn fields(&self) -> Vec<(&'static str, Value)> {
+        match self {
+            LanternEvent::Tick { days, population } => {
+                vec![("days", (*days).into()), ("population", population.into())]
+            }
+        }
+    }
+}
+
+impl Simulation for LanternWorld {
+    type Snapshot = Vec<usize>;
+
+    fn step(&mut self) {
+        self.tick()
+    }
+
+    fn snapshot(&self) -> Vec<usize> {
+        self.fish.clone()
+    }
+}
+
+/// Conditions are `day N` and `count OP N`, where OP is one of `>`, `>=`,
+/// `<`, `<=` and `=`, and N can be like `1e9`.
+impl Inspect for LanternWorld {
+    /// The fish and the day. The snapshot leaves the day out so it can
+    /// find cycles, but it's in the status.
+    type State = (Vec<usize>, usize);
+
+    fn status(&self) -> String {
+        format!("day {}, {} fish", self.day, self.count_big())
+    }
+
+    /// Once another tick would overflow a bucket there's nowhere to go.
+    fn finished(&self) -> bool {
+        self.next_fish().is_none()
+    }
+
+    fn state(&self) -> Self::State {
+        (self.fish.clone(), self.day)
+    }
+
+    fn restore(&mut self, (fish, day): &Self::State) {
+        self.fish.clone_from(fish);
+        self.day = *day;
+    }
+
+    /// How many fish have each timer, and each age too if they die. It's
+    /// only numbers, so there's no colour and it doesn't wrap.
+    fn inspect(&self, _width: usize, _colour: bool) -> String {
+        let timers = self.model.timers();
+        let mut rows = vec![(0..timers).map(|t| t.to_string()).collect::<Vec<_>>()];
+        let mut labels = vec!["timer".to_owned()];
+
+        for (age, fish) in self.fish.chunks(timers).enumerate() {
+            labels.push(match self.model.lifespan {
+                Some(_) => format!("age {}", age),
+                None => "fish".to_owned(),
+            });
+            rows.push(fish.iter().map(|f| f.to_string()).collect());
+        }
+
+        let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..timers)
+            .map(|t| rows.iter().map(|row| row[t].len()).max().unwrap_or(0))
+            .collect();
+
+        let mut out = format!("{}\n", self.status());
+        for (label, row) in labels.iter().zip(rows) {
+            out.push_str(&format!("{:<width$}", label, width = label_width));
+            for (cell, width) in row.iter().zip(&widths) {
+                out.push_str(&format!(" {:>width$}", cell, width = width));
+            }
+            out.push('\n');
+        }
+
+        out
+    }
+
+    fn condition(s: &str) -> Result<Condition<Self>, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+
+        match words[..] {
+            ["day", n] => {
+                let n: usize = n.parse().map_err(|_| format!("'{}' isn't a day", n))?;
+                Ok(Box::new(move |world: &Self| world.day >= n))
+            }
+            ["count", op, n] => {
+                let n = debugger::parse_number(n)?;
+                let op: fn(&BigUint, &BigUint) -> bool = match op {
+                    ">" => |a, b| a > b,
+                    ">=" => |a, b| a >= b,
+                    "<" => |a, b| a < b,
+                    "<=" => |a, b| a <= b,
+                    "=" | "==" => |a, b| a == b,
+                    _ => return Err(format!("'{}' isn't a comparison", op)),
+                };
+                Ok(Box::new(move |world: &Self| op(&world.count_big(), &n)))
+            }
+            _ => Err(format!(
+                "'{}' isn't a condition. Try 'day N' or 'count > N'",
+                s
+            )),
+        }
+    }
+}
+
+/// What [Matrix] can be made of. The checked operations return None on
+/// overflow, which is never for a BigUint.
+trait Element: Clone {
+    fn zero() -> Self;
+    fn from_usize(value: usize) -> Self;
+    fn checked_add(&self, rhs: &Self) -> Option<Self>;
+    fn checked_mul(&self, rhs: &Self) -> Option<Self>;
+}
+
+impl Element for u128 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn from_usize(value: usize) -> Self {
+        value as u128
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        u128::checked_add(*self, *rhs)
     }
 
-    pub fn count(&self) -> usize {
-        self.fish.iter().sum()
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        u128::checked_mul(*self, *rhs)
+    }
+}
+
+impl Element for BigUint {
+    fn zero() -> Self {
+        BigUint::zero()
+    }
+
+    fn from_usize(value: usize) -> Self {
+        BigUint::from(value)
+    }
+
+    fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Some(self + rhs)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Some(self * rhs)
+    }
+}
+
+/// A square matrix, row after row.
+#[derive(Debug, Clone, PartialEq)]
+struct Matrix<T> {
+    size: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Element> Matrix<T> {
+    fn from_small(small: &[Vec<usize>]) -> Self {
+        Self {
+            size: small.len(),
+            cells: small.iter().flatten().map(|c| T::from_usize(*c)).collect(),
+        }
+    }
+
+    fn identity(size: usize) -> Self {
+        let mut cells = vec![T::zero(); size * size];
+        for idx in 0..size {
+            cells[idx * size + idx] = T::from_usize(1);
+        }
+
+        Self { size, cells }
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.size)
+    }
+
+    fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        let size = self.size;
+        let mut cells = Vec::with_capacity(size * size);
+        for row in 0..size {
+            for col in 0..size {
+                let mut cell = T::zero();
+                for k in 0..size {
+                    let product =
+                        self.cells[row * size + k].checked_mul(&rhs.cells[k * size + col])?;
+                    cell = cell.checked_add(&product)?;
+                }
+                cells.push(cell);
+            }
+        }
+
+        Some(Self { size, cells })
+    }
+
+    /// Exponentiation by squaring.
+    fn pow(&self, mut exp: u64) -> Option<Self> {
+        let mut result = Self::identity(self.size);
+        let mut base = self.clone();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.checked_mul(&base)?;
+            }
+
+            exp >>= 1;
+            if exp > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+
+        Some(result)
     }
 }
 
@@ -57,7 +673,7 @@ impl FromStr for LanternWorld {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut fish = [0; 9];
+        let mut fish = vec![];
         let lanterns: Vec<usize> = s
             .lines()
             .next()
@@ -67,9 +683,419 @@ impl FromStr for LanternWorld {
             .collect();
 
         for l in lanterns {
+            if fish.len() <= l {
+                fish.resize(l + 1, 0);
+            }
             fish[l] += 1;
         }
 
-        Ok(Self { fish })
+        Self::new(LanternModel::default(), &fish)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aoc2021::{
+        debugger::{Debugger, UNTIL_LIMIT},
+        differential::{first_divergence, random_trials},
+    };
+
+    use super::*;
+
+    /// The reference for [LanternWorld]: every fish is its own timer and
+    /// age, and ticking touches every one of them. Slow on purpose so it's
+    /// easy to believe.
+    struct NaiveLanternWorld {
+        model: LanternModel,
+        /// (timer, age)
+        fish: Vec<(usize, usize)>,
+    }
+
+    impl NaiveLanternWorld {
+        fn new(model: LanternModel, timers: &[usize]) -> Self {
+            Self {
+                model,
+                fish: timers.iter().map(|timer| (*timer, 0)).collect(),
+            }
+        }
+    }
+
+    /// Snapshots are counted into the same buckets as [LanternWorld]'s, so
+    /// they can be compared. Age is always zero if the fish live forever.
+    impl Simulation for NaiveLanternWorld {
+        type Snapshot = Vec<usize>;
+
+        fn snapshot(&self) -> Vec<usize> {
+            let timers = self.model.timers();
+            let mut buckets = vec![0; timers * self.model.ages()];
+            for (timer, age) in &self.fish {
+                let age = if self.model.lifespan.is_some() {
+                    *age
+                } else {
+                    0
+                };
+                buckets[age * timers + timer] += 1;
+            }
+
+            buckets
+        }
+
+        fn step(&mut self) {
+            let mut next = vec![];
+
+            for (timer, age) in &self.fish {
+                if let Some(lifespan) = self.model.lifespan {
+                    if age + 1 >= lifespan {
+                        continue;
+                    }
+                }
+
+                for _ in 0..self.model.spawn(*timer) {
+                    next.push((self.model.newborn, 0));
+                }
+
+                let timer = if *timer == 0 {
+                    self.model.reset
+                } else {
+                    timer - 1
+                };
+                next.push((timer, age + 1));
+            }
+
+            self.fish = next;
+        }
+    }
+
+    fn bucketed(timers: &[usize], model: LanternModel) -> LanternWorld {
+        let mut fish = vec![0; model.timers()];
+        for timer in timers {
+            fish[*timer] += 1;
+        }
+
+        LanternWorld::new(model, &fish).unwrap()
+    }
+
+    #[test]
+    fn ticking_disagrees_with_naive_fish() {
+        let result = random_trials(
+            6,
+            20,
+            100,
+            |rng| {
+                let count = rng.below(20) as usize;
+                (0..count)
+                    .map(|_| rng.below(9) as usize)
+                    .collect::<Vec<_>>()
+            },
+            |timers| bucketed(timers, LanternModel::default()),
+            |timers| NaiveLanternWorld::new(LanternModel::default(), timers),
+        );
+
+        if let Err(mismatch) = result {
+            panic!("{}", mismatch);
+        }
+    }
+
+    #[test]
+    fn ticking_disagrees_with_naive_fish_on_random_models() {
+        let result = random_trials(
+            34,
+            50,
+            30,
+            |rng| {
+                // At most two babies every three days and only 30 days of
+                // it, or the naive world stops fitting in memory. A lifespan
+                // doesn't help, they have plenty of babies before they die.
+                let spawn_timers = rng.below(2) + 1;
+                let spawns = (0..spawn_timers).map(|_| rng.below(2) as usize).collect();
+                let model = LanternModel {
+                    reset: rng.below(7) as usize + 2,
+                    newborn: rng.below(10) as usize,
+                    lifespan: Some(rng.below(15) as usize + 1),
+                    spawns,
+                };
+                let count = rng.below(5) as usize;
+                let timers: Vec<usize> = (0..count)
+                    .map(|_| rng.below(model.timers() as u64) as usize)
+                    .collect();
+
+                (model, timers)
+            },
+            |(model, timers)| bucketed(timers, model.clone()),
+            |(model, timers)| NaiveLanternWorld::new(model.clone(), timers),
+        );
+
+        if let Err(mismatch) = result {
+            panic!("{}", mismatch);
+        }
+    }
+
+    #[test]
+    fn divergence_is_reported_on_wrong_day() {
+        // Babies at seven instead of eight. The spawn at eight keeps the
+        // timers going up to eight so the histograms line up on day zero.
+        let wrong: LanternModel = "newborn=7,spawn=0:1,spawn=8:0".parse().unwrap();
+        let mut fast = bucketed(&[0], LanternModel::default());
+        let mut naive = NaiveLanternWorld::new(wrong, &[0]);
+
+        let divergence = first_divergence(&mut fast, &mut naive, 100).unwrap();
+        assert_eq!(divergence.step, 1);
+        assert_eq!(divergence.fast, vec![0, 0, 0, 0, 0, 0, 1, 0, 1]);
+        assert_eq!(divergence.naive, vec![0, 0, 0, 0, 0, 0, 1, 1, 0]);
+    }
+
+    #[test]
+    fn projection_disagrees_with_ticking() {
+        let mut world: LanternWorld = day_parse!();
+        let start = world.clone();
+
+        for day in 0..=256 {
+            assert_eq!(start.count_on_day(day), world.count().map(|c| c as u128));
+            assert_eq!(
+                start.project_matrix::<u128>(day),
+                world.count().map(|c| c as u128)
+            );
+            assert_eq!(
+                start.count_on_day_big(day).to_u128(),
+                world.count().map(|c| c as u128)
+            );
+            world.tick();
+        }
+
+        assert_eq!(start.count_on_day(80), Some(394994));
+        assert_eq!(start.count_on_day(256), Some(1765974267455));
+    }
+
+    #[test]
+    fn big_projection_disagrees_with_u128() {
+        let world: LanternWorld = day_parse!();
+
+        let last_fit = (0..).find(|day| world.count_on_day(day + 1).is_none());
+        let last_fit = last_fit.unwrap();
+        assert_eq!(
+            world.count_on_day_big(last_fit).to_u128(),
+            world.count_on_day(last_fit)
+        );
+
+        // One more day and it doesn't fit anymore, but is still bigger
+        let past = world.count_on_day_big(last_fit + 1);
+        assert_eq!(past.to_u128(), None);
+        assert!(past.to_string().len() >= world.count_on_day(last_fit).unwrap().to_string().len());
+    }
+
+    #[test]
+    fn big_count_disagrees_with_usize() {
+        let full = LanternWorld::new(LanternModel::default(), &[usize::MAX; 9]).unwrap();
+
+        assert_eq!(full.count_big().to_u128(), Some(usize::MAX as u128 * 9));
+        assert_eq!(full.count(), None);
+    }
+
+    #[test]
+    fn default_model_has_wrong_transition() {
+        let world: LanternWorld = day_parse!();
+        let transition = world.transition();
+
+        assert_eq!(transition.len(), 9);
+        for (to, row) in transition.iter().enumerate() {
+            for (from, cell) in row.iter().enumerate() {
+                let expected = (from == to + 1 || (from == 0 && (to == 6 || to == 8))) as usize;
+                assert_eq!(*cell, expected, "transition[{}][{}]", to, from);
+            }
+        }
+    }
+
+    #[test]
+    fn model_variants_are_wrong() {
+        // Two babies a day while the timer is at zero, one while it's at one
+        let model: LanternModel = "reset=2,newborn=3,spawn=0:2,spawn=1:1".parse().unwrap();
+        let mut world = LanternWorld::new(model, &[1]).unwrap();
+
+        world.tick();
+        assert_eq!(world.histogram(), vec![0, 0, 1, 2]);
+        world.tick();
+        assert_eq!(world.histogram(), vec![0, 1, 2, 0]);
+        world.tick();
+        assert_eq!(world.histogram(), vec![1, 2, 0, 1]);
+        assert_eq!(world.count_on_day(0), Some(4));
+
+        let start = LanternWorld::new(world.model.clone(), &[1]).unwrap();
+        assert_eq!(start.count_on_day(3), Some(4));
+        assert_eq!(start.project_matrix::<u128>(3), Some(4));
+    }
+
+    #[test]
+    fn lifespan_model_is_wrong() {
+        let model: LanternModel = "lifespan=3".parse().unwrap();
+        let mut world = LanternWorld::new(model, &[1, 5]).unwrap();
+        let start = world.clone();
+
+        world.tick();
+        assert_eq!(world.histogram(), vec![5, 0, 0, 0, 0, 0, 1, 0, 1]);
+        world.tick();
+        assert_eq!(world.histogram(), vec![0, 0, 0, 0, 0, 1, 5, 1, 5]);
+        // The first fish die of old age, their babies live on
+        world.tick();
+        assert_eq!(world.histogram(), vec![0, 0, 0, 0, 0, 0, 1, 5, 0]);
+        assert_eq!(start.count_on_day(3), Some(6));
+        assert_eq!(start.project_matrix::<u128>(3), Some(6));
+    }
+
+    #[test]
+    fn projection_cost_is_wrong() {
+        assert!(ticking_is_cheaper(9, 256));
+        assert!(!ticking_is_cheaper(9, 10_000));
+        // lifespan=400000 has 3.6 million buckets, whose cube doesn't fit
+        assert!(ticking_is_cheaper(9 * 400_000, 256));
+        assert!(ticking_is_cheaper(9 * 400_000, u64::MAX));
+        assert!(ticking_is_cheaper(usize::MAX, 1));
+    }
+
+    #[test]
+    fn precomputed_transitions_are_wrong() {
+        let model: LanternModel = "lifespan=2,spawn=0:1,spawn=3:2".parse().unwrap();
+        let world = LanternWorld::new(model.clone(), &[1]).unwrap();
+        assert_eq!(world.transitions, model.transitions());
+
+        // Age zero moves to age one, with babies from timers zero and three.
+        // Age one dies.
+        let timers = model.timers();
+        assert_eq!(world.transitions[0], vec![(timers + 6, 1), (8, 1)]);
+        assert_eq!(world.transitions[3], vec![(timers + 2, 1), (8, 2)]);
+        assert_eq!(world.transitions[timers], vec![]);
+    }
+
+    #[test]
+    fn histogram_csv_is_wrong() {
+        let world = LanternWorld::new(LanternModel::default(), &[1, 0, 0, 1]).unwrap();
+        let mut csv = vec![];
+        world.write_histogram(2, &mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "day,0,1,2,3,4,5,6,7,8,total\n\
+             0,1,0,0,1,0,0,0,0,0,2\n\
+             1,0,0,1,0,0,0,1,0,1,3\n\
+             2,0,1,0,0,0,1,0,1,0,3\n"
+        );
+
+        // Well past where ticking would overflow
+        let world: LanternWorld = day_parse!();
+        let mut csv = vec![];
+        world.write_histogram(500, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let last = csv.lines().last().unwrap();
+        assert!(last.starts_with("500,"));
+        assert!(last.ends_with(&format!(",{}", world.count_on_day_big(500))));
+    }
+
+    #[test]
+    fn overflowing_tick_is_wrong() {
+        let world: LanternWorld = day_parse!();
+        let mut runner = Runner::new(world.clone());
+        let last = runner.run_until(|w| w.finished(), 1000).unwrap();
+
+        // Every day up to the last one ticked fits, and is right
+        assert!(last > 400);
+        assert_eq!(
+            runner.sim().count_big(),
+            world.count_on_day_big(last as u64)
+        );
+        assert_eq!(
+            runner.sim().count_big().to_string(),
+            runner.sim().count_string(true)
+        );
+
+        let mut sim = runner.sim().clone();
+        let panicked = std::panic::catch_unwind(move || sim.tick());
+        assert!(panicked.is_err());
+    }
+
+    #[test]
+    fn childless_fish_have_wrong_cycle() {
+        // Without babies the timers just go round and round, every seven
+        // days when they reset to six
+        let model: LanternModel = "spawn=0:0".parse().unwrap();
+        let world = LanternWorld::new(model, &[1, 2, 0, 3]).unwrap();
+        let mut runner = Runner::new(world);
+        runner.step_n(2);
+
+        let cycle = runner.find_cycle(1000).unwrap();
+        assert_eq!((cycle.start, cycle.length), (0, 7));
+
+        // Newborns at eight take a couple of days to join the loop
+        let model: LanternModel = "spawn=0:0".parse().unwrap();
+        let world = LanternWorld::new(model, &[0, 0, 0, 0, 0, 0, 0, 0, 4]).unwrap();
+        let cycle = Runner::new(world.clone()).find_cycle(1000).unwrap();
+        assert_eq!((cycle.start, cycle.length), (2, 7));
+
+        let mut runner = Runner::new(world.clone());
+        runner.fast_forward(1_000_000_000, 1000);
+        let mut expected = Runner::new(world);
+        expected.step_n(2 + (1_000_000_000 - 2) % 7);
+        assert_eq!(runner.sim().histogram(), expected.sim().histogram());
+        assert_eq!(runner.steps(), 1_000_000_000);
+    }
+
+    #[test]
+    fn growing_world_finds_cycle_or_stops_wrong() {
+        let world: LanternWorld = day_parse!();
+        let mut runner = Runner::new(world.clone());
+
+        assert_eq!(runner.find_cycle(200), None);
+
+        let million = runner
+            .run_until(|w| w.count().unwrap() > 1_000_000, 200)
+            .unwrap();
+        assert!(world.count_on_day(million as u64 - 1).unwrap() <= 1_000_000);
+        assert!(world.count_on_day(million as u64).unwrap() > 1_000_000);
+        assert_eq!(runner.fast_forward(256, 10), None);
+        assert_eq!(runner.sim().count(), Some(1765974267455));
+    }
+
+    #[test]
+    fn tick_events_have_wrong_population() {
+        let mut world: LanternWorld = "3,4,3,1,2".parse().unwrap();
+        let events = aoc2021::events::capture(|| {
+            for _ in 0..18 {
+                world.tick();
+            }
+        });
+
+        assert_eq!(events.len(), 18);
+        assert!(events[0].ends_with(r#""event":"tick","days":1,"population":5}"#));
+        assert!(events[17].ends_with(r#""event":"tick","days":18,"population":26}"#));
+    }
+
+    #[test]
+    fn debugger_disagrees_with_runner() {
+        let world: LanternWorld = "3,4,3,1,2".parse().unwrap();
+        let mut debugger = Debugger::new(world.clone());
+        let mut runner = Runner::new(world);
+
+        assert!(LanternWorld::condition("count >= 2.6e1").is_err());
+        let over = LanternWorld::condition("count >= 26e0").unwrap();
+        assert_eq!(debugger.run_until(&over, 100), Some(18));
+        assert_eq!(
+            runner.run_until(|w| w.count().unwrap() >= 26, 100),
+            Some(18)
+        );
+
+        // Going back and forth again doesn't step, it looks up what it saw
+        assert_eq!(debugger.goto(3, 0), 3);
+        assert_eq!(debugger.sim().histogram(), vec![2, 1, 0, 0, 0, 1, 1, 1, 1]);
+        assert_eq!(debugger.sim().status(), "day 3, 7 fish");
+        assert_eq!(debugger.recorded(), 18);
+        assert_eq!(debugger.goto(20, UNTIL_LIMIT), 20);
+        assert_eq!(debugger.recorded(), 20);
+        runner.step_n(2);
+        assert_eq!(debugger.sim().snapshot(), runner.sim().snapshot());
+        assert_eq!(debugger.sim().status(), "day 20, 34 fish");
+
+        // Only so many new steps at a time
+        assert_eq!(debugger.goto(100, 5), 25);
+        runner.step_n(5);
+        assert_eq!(debugger.sim().snapshot(), runner.sim().snapshot());
+        assert_eq!(debugger.sim().status(), "day 25, 47 fish");
     }
 }
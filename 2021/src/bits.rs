@@ -0,0 +1,138 @@
+use core::fmt;
+
+/// How many words it takes to hold `width` bits. Always at least one so a
+/// zero width number still has somewhere to put its nothing.
+pub fn words_for(width: usize) -> usize {
+    width.div_ceil(64).max(1)
+}
+
+/// The mask of the bits that are actually in use in word `word` of a
+/// `width` wide number.
+pub fn word_mask(width: usize, word: usize) -> u64 {
+    let used = width.saturating_sub(word * 64);
+    if used >= 64 {
+        u64::MAX
+    } else {
+        (1 << used) - 1
+    }
+}
+
+/// An owned, fixed-width binary number. Packed least significant word first
+/// and least significant bit first inside each word, so `words[0] & 1` is
+/// the rightmost digit of the number as the puzzle writes it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bits {
+    width: usize,
+    words: Vec<u64>,
+}
+
+impl Bits {
+    pub fn zero(width: usize) -> Self {
+        Self {
+            width,
+            words: vec![0; words_for(width)],
+        }
+    }
+
+    /// Takes packed words as described on [Bits]. Any bits set past `width`
+    /// are cleared.
+    pub fn from_words(width: usize, mut words: Vec<u64>) -> Self {
+        words.resize(words_for(width), 0);
+        for (idx, word) in words.iter_mut().enumerate() {
+            *word &= word_mask(width, idx);
+        }
+
+        Self { width, words }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// From digits written most significant first, like the puzzle input.
+    pub fn from_msb(digits: &[u8]) -> Self {
+        let mut bits = Self::zero(digits.len());
+        for (position, digit) in digits.iter().enumerate() {
+            bits.set(digits.len() - 1 - position, *digit == 1);
+        }
+
+        bits
+    }
+
+    /// From digits written least significant first.
+    pub fn from_lsb(digits: &[u8]) -> Self {
+        let mut bits = Self::zero(digits.len());
+        for (bit, digit) in digits.iter().enumerate() {
+            bits.set(bit, *digit == 1);
+        }
+
+        bits
+    }
+
+    /// Most significant digit first, like the puzzle input.
+    pub fn to_msb(&self) -> Vec<u8> {
+        let mut digits = self.to_lsb();
+        digits.reverse();
+        digits
+    }
+
+    /// Least significant digit first.
+    pub fn to_lsb(&self) -> Vec<u8> {
+        (0..self.width).map(|bit| self.bit(bit) as u8).collect()
+    }
+
+    /// Bit `bit`, counting up from the least significant.
+    pub fn bit(&self, bit: usize) -> bool {
+        self.words[bit / 64] & (1 << (bit % 64)) != 0
+    }
+
+    pub fn set(&mut self, bit: usize, value: bool) {
+        let mask = 1 << (bit % 64);
+        if value {
+            self.words[bit / 64] |= mask;
+        } else {
+            self.words[bit / 64] &= !mask;
+        }
+    }
+
+    /// Every bit flipped, but only the bits inside the width. The unused top
+    /// of the last word stays zero so equality keeps working.
+    pub fn not(&self) -> Self {
+        Self {
+            width: self.width,
+            words: self
+                .words
+                .iter()
+                .enumerate()
+                .map(|(idx, word)| !word & word_mask(self.width, idx))
+                .collect(),
+        }
+    }
+
+    /// None if any bit past the 64th is set.
+    pub fn to_u64(&self) -> Option<u64> {
+        if self.words[1..].iter().any(|w| *w != 0) {
+            None
+        } else {
+            Some(self.words[0])
+        }
+    }
+
+    pub fn to_usize(&self) -> Option<usize> {
+        self.to_u64().and_then(|v| usize::try_from(v).ok())
+    }
+}
+
+impl fmt::Display for Bits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for digit in self.to_msb() {
+            write!(f, "{}", digit)?;
+        }
+
+        Ok(())
+    }
+}
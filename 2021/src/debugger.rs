@@ -0,0 +1,329 @@
+//! Step a [Simulation] forwards and backwards from a terminal, or anything
+//! else that reads lines.
+//!
+//! ```text
+//! [0]> until any won
+//! [12] draw 11 was 24, 1 board won
+//! [12]> back
+//! [11] draw 10 was 21, no boards won
+//! ```
+//!
+//! Every step's [Inspect::State] is kept, so going back restores one rather
+//! than replaying anything. `next`, `goto` and `until` stop once the
+//! simulation's [finished](Inspect::finished), and take at most
+//! [UNTIL_LIMIT] new steps.
+
+use std::io::{self, BufRead, Write};
+
+use crate::{bigint::BigUint, simulation::Simulation};
+
+/// How many new steps `next`, `goto` or `until` will take before giving up.
+pub const UNTIL_LIMIT: usize = 10_000;
+
+/// A simulation that can be looked at in the debugger.
+pub trait Inspect: Simulation {
+    /// Everything [Inspect::restore] needs to put us back here. Unlike a
+    /// [Simulation::Snapshot] it can't leave out anything that's shown, like
+    /// how many steps have been taken.
+    type State;
+
+    /// One line about where we are, printed after every move.
+    fn status(&self) -> String;
+
+    /// Everything worth seeing, for `show`, in `width` columns and with
+    /// colour if `colour`.
+    fn inspect(&self, width: usize, colour: bool) -> String;
+
+    /// Whether stepping any more would do nothing, so `next`, `goto` and
+    /// `until` can stop.
+    fn finished(&self) -> bool {
+        false
+    }
+
+    fn state(&self) -> Self::State;
+
+    fn restore(&mut self, state: &Self::State);
+
+    /// What `until` stops on, like `any won` or `count > 1e9`. The error
+    /// says what conditions there are.
+    fn condition(s: &str) -> Result<Condition<Self>, String>;
+}
+
+pub type Condition<S> = Box<dyn Fn(&S) -> bool>;
+
+/// Every state we've been in, so going back is restoring one.
+/// Going forward again after going back restores what's recorded rather
+/// than stepping.
+#[derive(Debug, Clone)]
+pub struct Debugger<S: Inspect> {
+    sim: S,
+    history: Vec<S::State>,
+    at: usize,
+}
+
+impl<S: Inspect> Debugger<S> {
+    pub fn new(sim: S) -> Self {
+        Self {
+            history: vec![sim.state()],
+            sim,
+            at: 0,
+        }
+    }
+
+    pub fn sim(&self) -> &S {
+        &self.sim
+    }
+
+    /// How many steps from the start we are.
+    pub fn at(&self) -> usize {
+        self.at
+    }
+
+    /// How many steps have been recorded, however far back we've gone since.
+    pub fn recorded(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    /// Restore a recorded step.
+    fn jump(&mut self, step: usize) {
+        self.sim.restore(&self.history[step]);
+        self.at = step;
+    }
+
+    /// False if the simulation's finished and there's nowhere to go.
+    pub fn forward(&mut self) -> bool {
+        if self.at < self.recorded() {
+            self.jump(self.at + 1);
+            return true;
+        }
+
+        if self.sim.finished() {
+            return false;
+        }
+
+        self.sim.step();
+        self.history.push(self.sim.state());
+        self.at += 1;
+        true
+    }
+
+    /// False if we're already at the start.
+    pub fn back(&mut self) -> bool {
+        if self.at == 0 {
+            return false;
+        }
+
+        self.jump(self.at - 1);
+        true
+    }
+
+    /// Go to step `step`, forwards or back, taking no more than `limit` steps
+    /// past what's recorded and stopping if it finishes. Where we got to.
+    pub fn goto(&mut self, step: usize, limit: usize) -> usize {
+        if step <= self.recorded() {
+            self.jump(step);
+            return self.at;
+        }
+
+        let recorded = self.recorded();
+        self.jump(recorded);
+        for _ in 0..limit.min(step - recorded) {
+            if !self.forward() {
+                break;
+            }
+        }
+
+        self.at
+    }
+
+    /// Step until `predicate` is true, checking before every step, but no
+    /// more than `limit` steps. Where we stopped if it was ever true, like
+    /// [Runner::run_until](crate::simulation::Runner::run_until). It's never
+    /// true if the simulation finishes first.
+    pub fn run_until<P: FnMut(&S) -> bool>(
+        &mut self,
+        mut predicate: P,
+        limit: usize,
+    ) -> Option<usize> {
+        for taken in 0..=limit {
+            if predicate(self.sim()) {
+                return Some(self.at);
+            }
+
+            if taken < limit && !self.forward() {
+                break;
+            }
+        }
+
+        None
+    }
+}
+
+/// What can be typed at the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Forward(usize),
+    Back(usize),
+    Goto(usize),
+    Until(String),
+    Show,
+    Help,
+    Quit,
+}
+
+impl std::str::FromStr for Command {
+    type Err = String;
+
+    /// An empty line is one step forward, like pressing enter in gdb.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (word, rest) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let rest = rest.trim();
+
+        let count = |default: usize| -> Result<usize, String> {
+            match rest {
+                "" => Ok(default),
+                n => n
+                    .parse()
+                    .map_err(|_| format!("'{}' isn't a number of steps", n)),
+            }
+        };
+
+        match word {
+            "" | "n" | "next" | "f" | "forward" => count(1).map(Command::Forward),
+            "b" | "back" => count(1).map(Command::Back),
+            "g" | "goto" if !rest.is_empty() => count(0).map(Command::Goto),
+            "g" | "goto" => Err("goto which step?".into()),
+            "u" | "until" if !rest.is_empty() => Ok(Command::Until(rest.to_owned())),
+            "u" | "until" => Err("until what?".into()),
+            "s" | "show" | "p" | "print" => Ok(Command::Show),
+            "h" | "help" | "?" => Ok(Command::Help),
+            "q" | "quit" | "exit" => Ok(Command::Quit),
+            _ => Err(format!("'{}' isn't a command, try help", word)),
+        }
+    }
+}
+
+const HELP: &str = "\
+next [N]    step forward N steps, or one. So does an empty line
+back [N]    step back N steps, or one
+goto N      go to step N
+until COND  step until COND is true
+show        show everything
+quit        stop";
+
+/// Read commands from `input` until it runs out or says quit, writing what
+/// happens to `out`. The prompt has the step we're at in it. `show` fits in
+/// `width` columns and only has colour if `colour`, since `out` mightn't be
+/// a terminal.
+pub fn run<S: Inspect, R: BufRead, W: Write>(
+    sim: S,
+    input: R,
+    mut out: W,
+    width: usize,
+    colour: bool,
+) -> io::Result<()> {
+    let mut debugger = Debugger::new(sim);
+    writeln!(out, "[{}] {}", debugger.at(), debugger.sim().status())?;
+
+    let mut lines = input.lines();
+    loop {
+        write!(out, "[{}]> ", debugger.at())?;
+        out.flush()?;
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let command = match line.parse() {
+            Ok(command) => command,
+            Err(e) => {
+                writeln!(out, "{}", e)?;
+                continue;
+            }
+        };
+
+        match command {
+            Command::Forward(n) => {
+                let step = debugger.at().saturating_add(n);
+                goto(&mut debugger, step, &mut out)?;
+            }
+            Command::Back(n) => {
+                if n > debugger.at() {
+                    writeln!(out, "at the start already")?;
+                }
+                debugger.goto(debugger.at().saturating_sub(n), 0);
+            }
+            Command::Goto(step) => goto(&mut debugger, step, &mut out)?,
+            Command::Until(condition) => {
+                let condition = match S::condition(&condition) {
+                    Ok(condition) => condition,
+                    Err(e) => {
+                        writeln!(out, "{}", e)?;
+                        continue;
+                    }
+                };
+                let stop = |sim: &S| condition(sim) || sim.finished();
+
+                match debugger.run_until(stop, UNTIL_LIMIT) {
+                    Some(_) if !condition(debugger.sim()) => {
+                        writeln!(out, "finished without that happening")?
+                    }
+                    Some(_) => (),
+                    None => writeln!(out, "still not true after {} steps", UNTIL_LIMIT)?,
+                }
+            }
+            Command::Show => {
+                write!(out, "{}", debugger.sim().inspect(width, colour))?;
+                continue;
+            }
+            Command::Help => {
+                writeln!(out, "{}", HELP)?;
+                continue;
+            }
+            Command::Quit => break,
+        }
+
+        writeln!(out, "[{}] {}", debugger.at(), debugger.sim().status())?;
+    }
+
+    Ok(())
+}
+
+/// Go to `step` for `next` and `goto`, saying why if we couldn't get there.
+fn goto<S: Inspect, W: Write>(
+    debugger: &mut Debugger<S>,
+    step: usize,
+    mut out: W,
+) -> io::Result<()> {
+    let from = debugger.recorded();
+    if debugger.goto(step, UNTIL_LIMIT) == step {
+        Ok(())
+    } else if debugger.sim().finished() {
+        writeln!(
+            out,
+            "finished, there's nothing after step {}",
+            debugger.at()
+        )
+    } else {
+        writeln!(
+            out,
+            "gave up after {} new steps",
+            debugger.recorded() - from
+        )
+    }
+}
+
+/// A whole number for a condition, written out or like `1e9`.
+pub fn parse_number(s: &str) -> Result<BigUint, String> {
+    let bad = || format!("'{}' isn't a whole number", s);
+
+    match s.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => {
+            let mantissa: BigUint = mantissa.parse().map_err(|_| bad())?;
+            let exp: u32 = exp.parse().map_err(|_| bad())?;
+            Ok(mantissa * BigUint::from(10u128).pow(exp))
+        }
+        None => s.parse().map_err(|_| bad()),
+    }
+}
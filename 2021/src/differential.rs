@@ -0,0 +1,114 @@
+//! Run a fast implementation and a naive one side by side and find the
+//! first step where they stop agreeing.
+//!
+//! Both sides are a [Simulation], and they agree if their snapshots are
+//! the same after every step.
+
+use core::fmt;
+
+use crate::{rng::Rng, simulation::Simulation};
+
+/// The first step two implementations disagreed on and what they each saw.
+/// Step zero is before either has stepped at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence<T> {
+    pub step: usize,
+    pub fast: T,
+    pub naive: T,
+}
+
+impl<T: fmt::Debug> fmt::Display for Divergence<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "diverged on step {}: fast saw {:?} but naive saw {:?}",
+            self.step, self.fast, self.naive
+        )
+    }
+}
+
+/// Snapshot both, step both, for `steps` steps. None if they always agreed.
+pub fn first_divergence<A, B>(
+    fast: &mut A,
+    naive: &mut B,
+    steps: usize,
+) -> Option<Divergence<A::Snapshot>>
+where
+    A: Simulation,
+    A::Snapshot: fmt::Debug,
+    B: Simulation<Snapshot = A::Snapshot>,
+{
+    for step in 0..=steps {
+        let (seen_fast, seen_naive) = (fast.snapshot(), naive.snapshot());
+        if seen_fast != seen_naive {
+            return Some(Divergence {
+                step,
+                fast: seen_fast,
+                naive: seen_naive,
+            });
+        }
+
+        if step < steps {
+            fast.step();
+            naive.step();
+        }
+    }
+
+    None
+}
+
+/// A trial that diverged, and the input it was built from so it can be
+/// reproduced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch<S, T> {
+    pub trial: usize,
+    pub input: S,
+    pub divergence: Divergence<T>,
+}
+
+impl<S: fmt::Debug, T: fmt::Debug> fmt::Display for Mismatch<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "trial {} {} (input {:?})",
+            self.trial, self.divergence, self.input
+        )
+    }
+}
+
+/// `trials` times: make an input with `generate`, build both
+/// implementations from it, and run them for `steps` steps. Stops at the
+/// first trial that diverges. The same seed always makes the same inputs.
+pub fn random_trials<S, A, B, G, F, N>(
+    seed: u64,
+    trials: usize,
+    steps: usize,
+    mut generate: G,
+    fast: F,
+    naive: N,
+) -> Result<(), Mismatch<S, A::Snapshot>>
+where
+    A: Simulation,
+    A::Snapshot: fmt::Debug,
+    B: Simulation<Snapshot = A::Snapshot>,
+    G: FnMut(&mut Rng) -> S,
+    F: Fn(&S) -> A,
+    N: Fn(&S) -> B,
+{
+    let mut rng = Rng::new(seed);
+
+    for trial in 0..trials {
+        let input = generate(&mut rng);
+        let (mut a, mut b) = (fast(&input), naive(&input));
+
+        if let Some(divergence) = first_divergence(&mut a, &mut b, steps) {
+            return Err(Mismatch {
+                trial,
+                input,
+                divergence,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+//! What happened while solving, one JSON object a line, for visualising,
+//! stepping through or diffing runs. Nothing's written without `--events
+//! path` or [ENV]. A line looks like
+//!
+//! ```text
+//! {"day":"day4","seq":0,"event":"draw","draw":0,"number":7}
+//! ```
+//!
+//! Day 3 has `filtered`, or `split` with `--method original`. Day 4 has
+//! `draw`, `marked` and `won`, but not from `--monte-carlo`. Day 6 has `tick`
+//! with how many `days` have gone by.
+
+use core::fmt;
+use std::{
+    cell::{Cell, RefCell},
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use crate::{arg_value, bigint::BigUint};
+
+/// The environment variable naming a file to write events to, when there's
+/// no `--events path` on the command line.
+pub const ENV: &str = "AOC_EVENTS";
+
+/// Something that happened while solving, written out as one line of JSON so
+/// a run can be looked at again later: replayed, drawn, or diffed against
+/// another one. Every line has the `day` it came from, a `seq` number
+/// counting up from zero across the run, the `event` kind, and then the
+/// fields.
+pub trait Event {
+    /// What sort of event it is, like `draw` or `won`.
+    fn kind(&self) -> &'static str;
+
+    fn fields(&self) -> Vec<(&'static str, Value)>;
+}
+
+/// Just enough JSON for an event's fields.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Kept as the digits so no size of number loses anything
+    Number(String),
+    String(String),
+    List(Vec<Value>),
+}
+
+macro_rules! value_from_number {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Value {
+                fn from(n: $t) -> Self {
+                    Value::Number(n.to_string())
+                }
+            }
+        )*
+    };
+}
+
+value_from_number!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl From<f64> for Value {
+    /// JSON doesn't have NaN or the infinities, so they're null.
+    fn from(n: f64) -> Self {
+        if n.is_finite() {
+            Value::Number(n.to_string())
+        } else {
+            Value::Null
+        }
+    }
+}
+
+impl From<&BigUint> for Value {
+    fn from(n: &BigUint) -> Self {
+        Value::Number(n.to_string())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(list: Vec<T>) -> Self {
+        Value::List(list.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map(Into::into).unwrap_or(Value::Null)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write_string(f, s),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (idx, value) in list.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// The line `event` is written as, without the newline.
+pub fn to_json<E: Event + ?Sized>(day: &str, seq: u64, event: &E) -> String {
+    let mut fields = vec![
+        ("day", Value::from(day)),
+        ("seq", Value::from(seq)),
+        ("event", Value::from(event.kind())),
+    ];
+    fields.extend(event.fields());
+
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(name, value)| format!("{}:{}", Value::from(*name), value))
+        .collect();
+
+    format!("{{{}}}", fields.join(","))
+}
+
+static SINK: OnceLock<Option<Mutex<BufWriter<File>>>> = OnceLock::new();
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static CAPTURE: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+    static MUTED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// The file from `--events` or [ENV], opened the first time anyone asks.
+///
+/// # Panics
+/// If the file can't be created. Asking for events and not getting them
+/// would be worse.
+fn sink() -> &'static Option<Mutex<BufWriter<File>>> {
+    SINK.get_or_init(|| {
+        let path = arg_value::<String>("--events")
+            .unwrap()
+            .or_else(|| std::env::var(ENV).ok())?;
+        let file = File::create(&path)
+            .unwrap_or_else(|e| panic!("Couldn't create events file '{}': {}", path, e));
+
+        Some(Mutex::new(BufWriter::new(file)))
+    })
+}
+
+fn capturing() -> bool {
+    CAPTURE.with(|capture| capture.borrow().is_some())
+}
+
+/// Whether events are going anywhere. Check before building an expensive
+/// one; [event!](crate::event) does.
+pub fn enabled() -> bool {
+    !MUTED.with(Cell::get) && (capturing() || sink().is_some())
+}
+
+/// Write `event` out if events are going anywhere. It's buffered, see
+/// [FlushGuard] for getting it out.
+pub fn emit<E: Event + ?Sized>(day: &str, event: &E) {
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+
+    if capturing() {
+        CAPTURE.with(|capture| {
+            if let Some(lines) = capture.borrow_mut().as_mut() {
+                lines.push(to_json(day, seq, event));
+            }
+        });
+    } else if let Some(sink) = sink() {
+        let mut sink = sink.lock().unwrap();
+        let _ = writeln!(sink, "{}", to_json(day, seq, event));
+    }
+}
+
+/// Write out whatever's still buffered.
+pub fn flush() {
+    if let Some(Some(sink)) = SINK.get() {
+        // A panic while it was locked shouldn't lose what we've got
+        let mut sink = sink.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = sink.flush();
+    }
+}
+
+/// [flush]es when it's dropped. Hold one for the whole of `main` and the
+/// events file is complete however it ends, returning or panicking, except
+/// for [std::process::exit], which drops nothing.
+#[must_use = "it flushes as soon as it's dropped"]
+pub struct FlushGuard;
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        flush();
+    }
+}
+
+/// Run `f` and hand back every event it emitted on this thread instead of
+/// writing them out. For tests, which all run on their own threads.
+pub fn capture<F: FnOnce()>(f: F) -> Vec<String> {
+    let previous = CAPTURE.with(|capture| capture.borrow_mut().replace(vec![]));
+    f();
+    CAPTURE
+        .with(|capture| std::mem::replace(&mut *capture.borrow_mut(), previous))
+        .unwrap_or_default()
+}
+
+/// Run `f` with nothing emitted from it on this thread, for work like a
+/// Monte Carlo run whose events would drown out the ones anyone wanted.
+pub fn muted<R, F: FnOnce() -> R>(f: F) -> R {
+    let previous = MUTED.with(|muted| muted.replace(true));
+    let result = f();
+    MUTED.with(|muted| muted.set(previous));
+    result
+}
+
+/// Emit an event with the file it's from as the day, like
+/// `event!(BingoEvent::Draw { draw, number })`. The event isn't even built
+/// unless events are going somewhere.
+#[macro_export]
+macro_rules! event {
+    ($event:expr) => {
+        if $crate::events::enabled() {
+            $crate::events::emit($crate::log::target_of(file!()), &$event)
+        }
+    };
+}
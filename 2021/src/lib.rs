@@ -7,6 +7,21 @@ use std::{
     str::FromStr,
 };
 
+pub mod bigint;
+pub mod bits;
+pub mod debugger;
+pub mod differential;
+pub mod events;
+pub mod log;
+pub mod rng;
+pub mod simulation;
+pub mod sonar;
+pub mod svg;
+pub mod term;
+#[doc(hidden)]
+pub mod test_support;
+pub mod window;
+
 #[macro_export]
 macro_rules! day_input {
     () => {
@@ -91,6 +106,36 @@ where
     Ok(parsed)
 }
 
+/// Looks through the command line for `flag` and parses whatever follows it,
+/// so `--window 3` gives `Some(3)` for a flag of `--window`. Ok(None) if the
+/// flag isn't there at all.
+pub fn arg_value<T: FromStr>(flag: &str) -> Result<Option<T>, AdventError>
+where
+    <T as FromStr>::Err: fmt::Display,
+{
+    let mut args = std::env::args().skip_while(|arg| arg != flag);
+
+    if args.next().is_none() {
+        return Ok(None);
+    }
+
+    match args.next() {
+        Some(value) => value.parse().map(Some).map_err(|e| AdventError::Argument {
+            flag: flag.to_owned(),
+            inner: format!("{}", e),
+        }),
+        None => Err(AdventError::Argument {
+            flag: flag.to_owned(),
+            inner: "expected a value after it".into(),
+        }),
+    }
+}
+
+/// Whether `flag` was given on the command line at all.
+pub fn arg_flag(flag: &str) -> bool {
+    std::env::args().any(|arg| arg == flag)
+}
+
 #[derive(Debug)]
 pub enum AdventError {
     InvalidDay {
@@ -105,6 +150,10 @@ pub enum AdventError {
         file: PathBuf,
         line: Option<usize>,
     },
+    Argument {
+        flag: String,
+        inner: String,
+    },
 }
 
 impl AdventError {
@@ -161,6 +210,9 @@ impl fmt::Display for AdventError {
                     )
                 }
             }
+            AdventError::Argument { flag, inner } => {
+                write!(f, "Bad argument for '{}': {}", flag, inner)
+            }
         }
     }
 }
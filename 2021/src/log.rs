@@ -0,0 +1,284 @@
+//! Levelled logging to stderr, set from `AOC_LOG` and `-v`/`-q`. The 2023
+//! crate has its own copy in `2023/src/log.rs`. The two are kept the same,
+//! so a change to one goes in the other too.
+
+use core::fmt;
+use std::{io::Write, path::Path, str::FromStr, sync::OnceLock};
+
+/// The environment variable we read the log config from. It's a level for
+/// everything and levels for targets, like `info,day4=trace,bits=off`.
+pub const ENV: &str = "AOC_LOG";
+
+/// How much is said. Each level includes everything above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    const ALL: [Level; 5] = [
+        Level::Error,
+        Level::Warn,
+        Level::Info,
+        Level::Debug,
+        Level::Trace,
+    ];
+
+    /// `by` levels chattier, or quieter if it's negative. Stops at the ends.
+    fn shift(self, by: isize) -> Self {
+        let idx = (self as isize + by).clamp(0, Self::ALL.len() as isize - 1);
+        Self::ALL[idx as usize]
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            "trace" => Ok(Level::Trace),
+            _ => Err(format!("'{}' isn't a log level", s)),
+        }
+    }
+}
+
+/// Who gets to say what. A level of None is nothing at all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    default: Option<Level>,
+    targets: Vec<(String, Option<Level>)>,
+}
+
+impl Default for Config {
+    /// Warnings and errors from everyone
+    fn default() -> Self {
+        Self {
+            default: Some(Level::Warn),
+            targets: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// From the environment and then the command line. Each `-v` is one
+    /// level chattier for everything without its own level, up to `-vvv`,
+    /// and `-q` is errors only for everything, whatever its own level. Anything
+    /// turned off stays off.
+    pub fn from_env_and_args<I: IntoIterator<Item = String>>(env: Option<&str>, args: I) -> Self {
+        let mut config = match env.map(str::parse::<Config>) {
+            Some(Ok(config)) => config,
+            Some(Err(e)) => {
+                eprintln!("Ignoring {}: {}", ENV, e);
+                Config::default()
+            }
+            None => Config::default(),
+        };
+
+        let mut verbose = 0;
+        let mut quiet = false;
+        for arg in args.into_iter().filter(|arg| is_flag(arg)) {
+            match arg.as_str() {
+                "-q" | "--quiet" => quiet = true,
+                "--verbose" => verbose += 1,
+                vs => verbose += vs.len() as isize - 1,
+            }
+        }
+
+        if quiet {
+            let cap = |level: Option<Level>| level.map(|level| level.min(Level::Error));
+            config.default = cap(config.default);
+            for (_, level) in config.targets.iter_mut() {
+                *level = cap(*level);
+            }
+        } else if verbose > 0 {
+            config.default = Some(config.default.unwrap_or(Level::Error).shift(verbose));
+        }
+
+        config
+    }
+
+    pub fn level_for(&self, target: &str) -> Option<Level> {
+        self.targets
+            .iter()
+            .rev()
+            .find(|(t, _)| t == target)
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    pub fn enabled(&self, level: Level, target: &str) -> bool {
+        self.level_for(target).map(|l| level <= l).unwrap_or(false)
+    }
+}
+
+fn parse_level(s: &str) -> Result<Option<Level>, String> {
+    match s {
+        "off" => Ok(None),
+        s => s.parse().map(Some),
+    }
+}
+
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => config
+                    .targets
+                    .push((target.trim().to_owned(), parse_level(level.trim())?)),
+                None => config.default = parse_level(part)?,
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Whether `arg` is one of ours: `-q`, `--quiet`, `--verbose`, or `-v` with
+/// as many `v`s as you like.
+pub fn is_flag(arg: &str) -> bool {
+    match arg {
+        "-q" | "--quiet" | "--verbose" => true,
+        arg => arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v'),
+    }
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Read from [ENV] and the command line the first time anything's logged.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let env = std::env::var(ENV).ok();
+        Config::from_env_and_args(env.as_deref(), std::env::args().skip(1))
+    })
+}
+
+pub fn enabled(level: Level, target: &str) -> bool {
+    config().enabled(level, target)
+}
+
+/// What the macros call once they know it's [enabled]. Goes to stderr so
+/// stdout is only ever answers.
+pub fn write(level: Level, target: &str, args: fmt::Arguments) {
+    let _ = writeln!(std::io::stderr(), "[{} {}] {}", level, target, args);
+}
+
+/// `src/bin/day4.rs` is `day4`, `src/bits.rs` is `bits`. What the macros use
+/// as the target, from `file!()`.
+pub fn target_of(file: &'static str) -> &'static str {
+    Path::new(file)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(file)
+}
+
+/// Log at a [Level] with the file it's called from as the target, like
+/// `log!(Level::Info, "drew {}", n)`. The arguments aren't touched unless
+/// it's going to be written.
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {{
+        let target = $crate::log::target_of(file!());
+        if $crate::log::enabled($level, target) {
+            $crate::log::write($level, target, format_args!($($arg)+))
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Error, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Warn, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Info, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Debug, $($arg)+) };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => { $crate::log!($crate::log::Level::Trace, $($arg)+) };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn log_config_is_misread() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let config = Config::from_env_and_args(None, args(&["--exact"]));
+        assert_eq!(config.level_for("day4"), Some(Level::Warn));
+        assert!(!config.enabled(Level::Info, "day4"));
+
+        let config = Config::from_env_and_args(None, args(&["-v", "--day", "3", "-v"]));
+        assert_eq!(config.level_for("day4"), Some(Level::Debug));
+        let config = Config::from_env_and_args(None, args(&["-vvvvv"]));
+        assert_eq!(config.level_for("day4"), Some(Level::Trace));
+        let config = Config::from_env_and_args(None, args(&["-q"]));
+        assert_eq!(config.level_for("day4"), Some(Level::Error));
+
+        // targets keep their own levels whatever -v says
+        let env = Some("info, day4=trace ,bits=off");
+        let config = Config::from_env_and_args(env, args(&["-v"]));
+        assert_eq!(config.level_for("day6"), Some(Level::Debug));
+        assert_eq!(config.level_for("day4"), Some(Level::Trace));
+        assert_eq!(config.level_for("bits"), None);
+        assert!(!config.enabled(Level::Error, "bits"));
+
+        assert!("day4=loud".parse::<Config>().is_err());
+        assert_eq!(target_of(file!()), "log");
+    }
+
+    #[test]
+    fn quiet_is_not_errors_only() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        let config = Config::from_env_and_args(Some("warn,day4=trace,day6=off"), args(&["-q"]));
+        assert!(config.enabled(Level::Error, "day4"));
+        assert!(!config.enabled(Level::Warn, "day4"));
+        assert!(!config.enabled(Level::Warn, "day3"));
+        assert!(!config.enabled(Level::Error, "day6"));
+
+        // Quiet beats verbose
+        let config = Config::from_env_and_args(None, args(&["-vv", "-q"]));
+        assert!(config.enabled(Level::Error, "day1"));
+        assert!(!config.enabled(Level::Warn, "day1"));
+    }
+}
@@ -0,0 +1,40 @@
+/// A small seedable random number generator, SplitMix64. It's fast and the
+/// same seed always gives the same numbers, which is all we want out of it.
+/// Don't use it for anything that has to be unpredictable.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..bound`. This is the multiply-shift trick so it's a
+    /// tiny bit biased for huge bounds, which doesn't matter to us.
+    ///
+    /// # Panics
+    /// If `bound` is zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        assert!(bound != 0, "Rng::below called with a bound of zero");
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Fisher-Yates.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for idx in (1..items.len()).rev() {
+            let swap = self.below(idx as u64 + 1) as usize;
+            items.swap(idx, swap);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Things that step forward one state at a time, and ways to run them.
+
+use std::hash::Hash;
+
+pub trait Simulation {
+    /// Enough of the state that two equal snapshots mean the simulation
+    /// will do exactly the same thing from then on.
+    type Snapshot: Hash + Eq + Clone;
+
+    fn step(&mut self);
+    fn snapshot(&self) -> Self::Snapshot;
+}
+
+/// Where a simulation starts repeating itself. After `start` steps it's in
+/// a loop that comes back around every `length` steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+/// A simulation and how many steps it's taken.
+#[derive(Debug, Clone)]
+pub struct Runner<S> {
+    sim: S,
+    steps: usize,
+}
+
+impl<S: Simulation> Runner<S> {
+    pub fn new(sim: S) -> Self {
+        Self { sim, steps: 0 }
+    }
+
+    pub fn sim(&self) -> &S {
+        &self.sim
+    }
+
+    pub fn into_sim(self) -> S {
+        self.sim
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn step(&mut self) {
+        self.sim.step();
+        self.steps += 1;
+    }
+
+    pub fn step_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// Step until `predicate` is true, checking before every step, but no
+    /// more than `limit` steps. Returns how many steps the runner has taken
+    /// in total when it was true, or None if we hit the limit first.
+    pub fn run_until<P: FnMut(&S) -> bool>(
+        &mut self,
+        mut predicate: P,
+        limit: usize,
+    ) -> Option<usize> {
+        for taken in 0..=limit {
+            if predicate(&self.sim) {
+                return Some(self.steps);
+            }
+
+            if taken < limit {
+                self.step();
+            }
+        }
+
+        None
+    }
+
+    /// Brent's algorithm, counting from where we are now. None if we don't
+    /// see a repeat in `limit` steps. Works on copies so we stay put.
+    pub fn find_cycle(&self, limit: usize) -> Option<Cycle>
+    where
+        S: Clone,
+    {
+        // Find the length: the hare runs ahead in ever doubling stretches
+        // and the tortoise teleports to it at the start of every stretch.
+        let mut power = 1;
+        let mut length = 1;
+        let mut tortoise = self.sim.snapshot();
+        let mut hare = self.sim.clone();
+        hare.step();
+
+        let mut taken = 1;
+        while tortoise != hare.snapshot() {
+            if taken >= limit {
+                return None;
+            }
+
+            if power == length {
+                tortoise = hare.snapshot();
+                power *= 2;
+                length = 0;
+            }
+
+            hare.step();
+            length += 1;
+            taken += 1;
+        }
+
+        // Find the start: with the hare a cycle ahead they meet right where
+        // the cycle begins.
+        let mut tortoise = self.sim.clone();
+        let mut hare = self.sim.clone();
+        for _ in 0..length {
+            hare.step();
+        }
+
+        let mut start = 0;
+        while tortoise.snapshot() != hare.snapshot() {
+            tortoise.step();
+            hare.step();
+            start += 1;
+        }
+
+        Some(Cycle { start, length })
+    }
+
+    /// Get to `target` total steps. If the state repeats within
+    /// `search_limit` steps of here we skip every whole lap of the cycle, so
+    /// a target of a billion is cheap. If it doesn't we step the whole way.
+    ///
+    /// # Panics
+    /// If we're already past `target`.
+    pub fn fast_forward(&mut self, target: usize, search_limit: usize) -> Option<Cycle>
+    where
+        S: Clone,
+    {
+        assert!(
+            target >= self.steps,
+            "can't fast forward to step {}, we're at {} already",
+            target,
+            self.steps
+        );
+
+        let remaining = target - self.steps;
+        let cycle = self.find_cycle(search_limit.min(remaining));
+
+        match cycle {
+            Some(Cycle { start, length }) if remaining > start => {
+                self.step_n(start + (remaining - start) % length);
+                self.steps = target;
+            }
+            _ => self.step_n(remaining),
+        }
+
+        cycle
+    }
+}
+
+/// So a [Runner] can borrow a simulation rather than own it.
+impl<S: Simulation> Simulation for &mut S {
+    type Snapshot = S::Snapshot;
+
+    fn step(&mut self) {
+        (**self).step()
+    }
+
+    fn snapshot(&self) -> Self::Snapshot {
+        (**self).snapshot()
+    }
+}
@@ -0,0 +1,34 @@
+//! Day one's sonar sweep, for any window size.
+
+/// How many times the sum of `window` consecutive depths is bigger than the
+/// sum of the `window` depths before it. A window of one is part one, three
+/// is part two.
+pub fn summed_increases(depths: &[usize], window: usize) -> usize {
+    if depths.len() < window {
+        return 0;
+    }
+
+    let mut sum: usize = depths[..window].iter().sum();
+    let mut increases = 0;
+    for (leaving, entering) in depths.iter().zip(&depths[window..]) {
+        let next = sum + entering - leaving;
+        if next > sum {
+            increases += 1;
+        }
+
+        sum = next;
+    }
+
+    increases
+}
+
+/// The same answer as [summed_increases] without summing anything.
+/// Neighbouring windows share everything but their ends, so the sum goes up
+/// exactly when the depth coming in is bigger than the one going out.
+pub fn offset_increases(depths: &[usize], window: usize) -> usize {
+    depths
+        .iter()
+        .zip(depths.iter().skip(window))
+        .filter(|(leaving, entering)| entering > leaving)
+        .count()
+}
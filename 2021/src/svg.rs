@@ -0,0 +1,244 @@
+//! Just enough SVG to plot a day's answer. Numbers are written with at most
+//! two decimal places and trailing zeros left off, so the same plot is
+//! always the same text and can be checked against a snapshot.
+//!
+//! The tests check small examples against `snapshots/`. If a change to a
+//! plot is on purpose, run them with `UPDATE_SNAPSHOTS=1` and look at what
+//! they wrote.
+
+use core::fmt;
+use std::{fs, path::Path};
+
+/// A number the way it's written in an attribute.
+pub fn number(n: f64) -> String {
+    let text = format!("{:.2}", n);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+
+    match text {
+        "-0" => "0".to_owned(),
+        text => text.to_owned(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One tag, its attributes in the order they were given, and what's in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    name: &'static str,
+    attrs: Vec<(&'static str, String)>,
+    text: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            attrs: vec![],
+            text: None,
+            children: vec![],
+        }
+    }
+
+    pub fn attr<V: fmt::Display>(mut self, name: &'static str, value: V) -> Self {
+        self.attrs.push((name, escape(&value.to_string())));
+        self
+    }
+
+    /// An attribute that's a number, see [number]
+    pub fn num(self, name: &'static str, value: f64) -> Self {
+        self.attr(name, number(value))
+    }
+
+    pub fn text<S: AsRef<str>>(mut self, text: S) -> Self {
+        self.text = Some(escape(text.as_ref()));
+        self
+    }
+
+    pub fn child(mut self, child: Element) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn line(from: (f64, f64), to: (f64, f64)) -> Self {
+        Self::new("line")
+            .num("x1", from.0)
+            .num("y1", from.1)
+            .num("x2", to.0)
+            .num("y2", to.1)
+    }
+
+    /// Unfilled, so it's a line through every point
+    pub fn polyline(points: &[(f64, f64)]) -> Self {
+        let points: Vec<String> = points
+            .iter()
+            .map(|(x, y)| format!("{},{}", number(*x), number(*y)))
+            .collect();
+
+        Self::new("polyline")
+            .attr("points", points.join(" "))
+            .attr("fill", "none")
+    }
+
+    pub fn circle(centre: (f64, f64), radius: f64) -> Self {
+        Self::new("circle")
+            .num("cx", centre.0)
+            .num("cy", centre.1)
+            .num("r", radius)
+    }
+
+    pub fn label<S: AsRef<str>>(at: (f64, f64), text: S) -> Self {
+        Self::new("text").num("x", at.0).num("y", at.1).text(text)
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        write!(f, "{}<{}", indent, self.name)?;
+        for (name, value) in &self.attrs {
+            write!(f, " {}=\"{}\"", name, value)?;
+        }
+
+        match (&self.text, self.children.is_empty()) {
+            (None, true) => writeln!(f, "/>"),
+            (Some(text), true) => writeln!(f, ">{}</{}>", text, self.name),
+            (text, false) => {
+                writeln!(f, ">")?;
+                if let Some(text) = text {
+                    writeln!(f, "{}  {}", indent, text)?;
+                }
+                for child in &self.children {
+                    child.write(f, depth + 1)?;
+                }
+                writeln!(f, "{}</{}>", indent, self.name)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// A whole picture, `width` by `height` pixels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Svg {
+    width: f64,
+    height: f64,
+    elements: Vec<Element>,
+}
+
+impl Svg {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            elements: vec![],
+        }
+    }
+
+    pub fn push(&mut self, element: Element) {
+        self.elements.push(element);
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for Svg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\" font-size=\"12\">",
+            number(self.width),
+            number(self.height)
+        )?;
+        for element in &self.elements {
+            element.write(f, 1)?;
+        }
+        writeln!(f, "</svg>")
+    }
+}
+
+/// Turns data into pixels along one axis, in a straight line from `domain`
+/// onto `range`. Either can run backwards. A domain that's one value maps
+/// everything to the middle of the range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scale {
+    pub domain: (f64, f64),
+    pub range: (f64, f64),
+}
+
+impl Scale {
+    pub fn new(domain: (f64, f64), range: (f64, f64)) -> Self {
+        Self { domain, range }
+    }
+
+    /// From the smallest to the biggest of `values`, or 0 to 1 if there
+    /// aren't any.
+    pub fn fit<I: IntoIterator<Item = f64>>(values: I, range: (f64, f64)) -> Self {
+        let domain = values
+            .into_iter()
+            .fold(None, |bounds: Option<(f64, f64)>, v| match bounds {
+                None => Some((v, v)),
+                Some((lo, hi)) => Some((lo.min(v), hi.max(v))),
+            })
+            .unwrap_or((0.0, 1.0));
+
+        Self::new(domain, range)
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        let (d0, d1) = self.domain;
+        let (r0, r1) = self.range;
+
+        if d0 == d1 {
+            (r0 + r1) / 2.0
+        } else {
+            r0 + (value - d0) / (d1 - d0) * (r1 - r0)
+        }
+    }
+}
+
+/// An x axis along the bottom of the plot and a y axis down its left, each
+/// with its ends numbered and a name. The plot is wherever the scales'
+/// ranges put it.
+pub fn axes(x: &Scale, y: &Scale, x_name: &str, y_name: &str) -> Element {
+    let (left, right) = (x.range.0.min(x.range.1), x.range.0.max(x.range.1));
+    let (top, bottom) = (y.range.0.min(y.range.1), y.range.0.max(y.range.1));
+
+    let lines = Element::new("g")
+        .attr("stroke", "black")
+        .child(Element::line((left, bottom), (right, bottom)))
+        .child(Element::line((left, top), (left, bottom)));
+
+    let mut labels = Element::new("g").attr("fill", "black");
+    for end in [x.domain.0, x.domain.1] {
+        labels = labels.child(
+            Element::label((x.apply(end), bottom + 14.0), number(end))
+                .attr("text-anchor", "middle"),
+        );
+    }
+    for end in [y.domain.0, y.domain.1] {
+        labels = labels.child(
+            Element::label((left - 4.0, y.apply(end) + 4.0), number(end))
+                .attr("text-anchor", "end"),
+        );
+    }
+    labels = labels
+        .child(
+            Element::label(((left + right) / 2.0, bottom + 28.0), x_name)
+                .attr("text-anchor", "middle"),
+        )
+        .child(Element::label((left, top - 8.0), y_name).attr("text-anchor", "middle"));
+
+    Element::new("g").child(lines).child(labels)
+}
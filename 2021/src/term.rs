@@ -0,0 +1,133 @@
+//! Just enough terminal to colour things in and put them side by side.
+//!
+//! Colour is only for a terminal without `NO_COLOR` set, so whatever's drawn
+//! has to make sense without it too. Day four's boards put marked numbers
+//! `[in brackets]` and the winning line `*in stars*` instead of green and
+//! yellow. Side by side fits in `COLUMNS`, or 80 if it's not set.
+
+use std::io::IsTerminal;
+
+/// How something's drawn, as ANSI SGR codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Bold,
+    Dim,
+    Reverse,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+}
+
+impl Style {
+    fn code(&self) -> u8 {
+        match self {
+            Style::Bold => 1,
+            Style::Dim => 2,
+            Style::Reverse => 7,
+            Style::Red => 31,
+            Style::Green => 32,
+            Style::Yellow => 33,
+            Style::Blue => 34,
+        }
+    }
+}
+
+/// `text` in `styles`, or just `text` if we're not colouring.
+pub fn paint(text: &str, styles: &[Style], colour: bool) -> String {
+    if !colour || styles.is_empty() {
+        return text.to_owned();
+    }
+
+    let codes: Vec<String> = styles.iter().map(|s| s.code().to_string()).collect();
+    format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text)
+}
+
+/// Whether stdout wants colour: it's a terminal and `NO_COLOR` isn't set.
+pub fn stdout_colour() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// How wide the terminal is, from `COLUMNS`, or 80 if we can't tell.
+pub fn width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|c| *c > 0)
+        .unwrap_or(80)
+}
+
+/// How many columns `s` takes up once its escape codes are left out. Every
+/// char is one column, which is all we ever draw.
+pub fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Everything up to and including the letter that ends it
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+
+    width
+}
+
+/// Blocks of lines next to each other with `gap` spaces between, as many to
+/// a row as fit in `width`, and a blank line between rows. A block wider
+/// than `width` gets a row to itself. Short blocks are padded out at the
+/// bottom.
+pub fn side_by_side(blocks: &[Vec<String>], width: usize, gap: usize) -> String {
+    let widths: Vec<usize> = blocks
+        .iter()
+        .map(|b| b.iter().map(|l| visible_width(l)).max().unwrap_or(0))
+        .collect();
+
+    // Which blocks go on each row
+    let mut rows: Vec<Vec<usize>> = vec![];
+    let mut used = 0;
+    for (idx, block_width) in widths.iter().enumerate() {
+        match rows.last_mut() {
+            Some(row) if used + gap + block_width <= width => {
+                row.push(idx);
+                used += gap + block_width;
+            }
+            _ => {
+                rows.push(vec![idx]);
+                used = *block_width;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (row_idx, row) in rows.iter().enumerate() {
+        if row_idx > 0 {
+            out.push('\n');
+        }
+
+        let height = row.iter().map(|b| blocks[*b].len()).max().unwrap_or(0);
+        for line in 0..height {
+            let mut text = String::new();
+            for (pos, block) in row.iter().enumerate() {
+                let cell = blocks[*block].get(line).map(String::as_str).unwrap_or("");
+                text.push_str(cell);
+
+                // Pad to the block's width unless it's the last on the row
+                if pos + 1 < row.len() {
+                    let pad = widths[*block] - visible_width(cell) + gap;
+                    text.push_str(&" ".repeat(pad));
+                }
+            }
+            out.push_str(text.trim_end());
+            out.push('\n');
+        }
+    }
+
+    out
+}
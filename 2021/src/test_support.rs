@@ -0,0 +1,29 @@
+//! What the days' tests share. The bins' tests can only see the library as
+//! it's built for everyone, so this can't be `#[cfg(test)]`, but it's only
+//! for them.
+
+use std::{fs, path::Path};
+
+/// Check `actual` against the snapshot at `path`, which is from the crate's
+/// root however the tests are run. Set `UPDATE_SNAPSHOTS` to write `actual`
+/// there instead, after looking at it.
+///
+/// # Panics
+/// If they're different, or there's no snapshot and we're not updating.
+pub fn assert_snapshot(path: &str, actual: &str) {
+    let full = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&full, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&full)
+        .unwrap_or_else(|e| panic!("Couldn't read snapshot {}: {}", path, e));
+    assert!(
+        expected == actual,
+        "{} has changed, run with UPDATE_SNAPSHOTS=1 if that's right. Now it's:\n{}",
+        path,
+        actual
+    );
+}
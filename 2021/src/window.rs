@@ -0,0 +1,274 @@
+use std::{
+    collections::VecDeque,
+    ops::{Add, Sub},
+};
+
+/// Anything we can keep a running sum of. Implemented for the primitive
+/// numbers; `as f64` is all `to_f64` is, so it's lossy for big integers.
+pub trait Number: Copy + PartialOrd + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
+
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! number_impl {
+    ($zero:literal; $($t:ty),*) => {
+        $(
+            impl Number for $t {
+                const ZERO: Self = $zero;
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+number_impl!(0; u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+number_impl!(0.0; f32, f64);
+
+/// The last `N` values pushed into it. Sum and mean are kept as we go, so
+/// they're O(1), and min and max come off the front of a monotonic deque,
+/// which is amortized O(1) per push.
+///
+/// Floats will drift a little as the sum is added to and subtracted from
+/// rather than recomputed.
+#[derive(Debug, Clone)]
+pub struct SlidingWindow<T: Number, const N: usize> {
+    values: [T; N],
+    /// Where the next value goes, which is also the oldest once we're full
+    idx: usize,
+    len: usize,
+    sum: T,
+    /// How many values have ever been pushed. Tags the deque entries so we
+    /// know when they've fallen out of the window.
+    pushed: usize,
+    /// (push number, value), values increasing front to back
+    mins: VecDeque<(usize, T)>,
+    /// (push number, value), values decreasing front to back
+    maxes: VecDeque<(usize, T)>,
+}
+
+impl<T: Number, const N: usize> SlidingWindow<T, N> {
+    /// # Panics
+    /// If `N` is zero.
+    pub fn new() -> Self {
+        assert!(N > 0, "a SlidingWindow needs room for at least one value");
+
+        Self {
+            values: [T::ZERO; N],
+            idx: 0,
+            len: 0,
+            sum: T::ZERO,
+            pushed: 0,
+            mins: VecDeque::with_capacity(N),
+            maxes: VecDeque::with_capacity(N),
+        }
+    }
+
+    /// Add a value, returning the one that fell out the back if we were
+    /// already full.
+    pub fn push(&mut self, value: T) -> Option<T> {
+        let evicted = if self.len == N {
+            let old = self.values[self.idx];
+            self.sum = self.sum - old;
+            Some(old)
+        } else {
+            self.len += 1;
+            None
+        };
+
+        self.values[self.idx] = value;
+        self.sum = self.sum + value;
+        self.idx = (self.idx + 1) % N;
+
+        let oldest = (self.pushed + 1).saturating_sub(N);
+        Self::monotonic_push(&mut self.mins, self.pushed, value, oldest, |back| {
+            back >= value
+        });
+        Self::monotonic_push(&mut self.maxes, self.pushed, value, oldest, |back| {
+            back <= value
+        });
+        self.pushed += 1;
+
+        evicted
+    }
+
+    /// Drop everything from the back that `value` beats, then anything at
+    /// the front older than the window.
+    fn monotonic_push<F: Fn(T) -> bool>(
+        deque: &mut VecDeque<(usize, T)>,
+        pushed: usize,
+        value: T,
+        oldest: usize,
+        beaten: F,
+    ) {
+        while deque.back().map(|(_, back)| beaten(*back)).unwrap_or(false) {
+            deque.pop_back();
+        }
+        deque.push_back((pushed, value));
+
+        while deque.front().map(|(at, _)| *at < oldest).unwrap_or(false) {
+            deque.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn sum(&self) -> T {
+        self.sum
+    }
+
+    /// None while empty.
+    pub fn mean(&self) -> Option<f64> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.sum.to_f64() / self.len as f64)
+        }
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.mins.front().map(|(_, v)| *v)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.maxes.front().map(|(_, v)| *v)
+    }
+
+    /// The values oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        let start = if self.is_full() { self.idx } else { 0 };
+        (0..self.len).map(move |i| &self.values[(start + i) % N])
+    }
+
+    pub fn aggregates(&self) -> Option<Aggregates<T>> {
+        Some(Aggregates {
+            sum: self.sum,
+            min: self.min()?,
+            max: self.max()?,
+            len: self.len,
+        })
+    }
+}
+
+impl<T: Number, const N: usize> Default for SlidingWindow<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A snapshot of a full [SlidingWindow], what [Sliding] yields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aggregates<T> {
+    pub sum: T,
+    pub min: T,
+    pub max: T,
+    pub len: usize,
+}
+
+impl<T: Number> Aggregates<T> {
+    pub fn mean(&self) -> f64 {
+        self.sum.to_f64() / self.len as f64
+    }
+}
+
+/// Yields the aggregates of every full window over an iterator, so `N - 1`
+/// fewer items than it was given. Make one with [SlidingExt::sliding].
+pub struct Sliding<I: Iterator, const N: usize>
+where
+    I::Item: Number,
+{
+    inner: I,
+    window: SlidingWindow<I::Item, N>,
+}
+
+impl<I: Iterator, const N: usize> Iterator for Sliding<I, N>
+where
+    I::Item: Number,
+{
+    type Item = Aggregates<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.window.push(self.inner.next()?);
+
+            if self.window.is_full() {
+                return self.window.aggregates();
+            }
+        }
+    }
+}
+
+pub trait SlidingExt: Iterator + Sized
+where
+    Self::Item: Number,
+{
+    /// `[1, 2, 3, 4].into_iter().sliding::<3>()` gives the aggregates of
+    /// `[1, 2, 3]` and then `[2, 3, 4]`.
+    fn sliding<const N: usize>(self) -> Sliding<Self, N> {
+        Sliding {
+            inner: self,
+            window: SlidingWindow::new(),
+        }
+    }
+}
+
+impl<I: Iterator> SlidingExt for I where I::Item: Number {}
+
+#[cfg(test)]
+mod test {
+    use crate::rng::Rng;
+
+    use super::*;
+
+    #[test]
+    fn sliding_window_has_wrong_aggregates() {
+        let mut window: SlidingWindow<i32, 3> = SlidingWindow::new();
+
+        assert_eq!(window.push(5), None);
+        assert_eq!(window.push(-2), None);
+        assert_eq!(
+            (window.sum(), window.min(), window.max()),
+            (3, Some(-2), Some(5))
+        );
+        assert_eq!(window.push(7), None);
+        assert_eq!(window.push(1), Some(5));
+        assert_eq!(
+            (window.sum(), window.min(), window.max()),
+            (6, Some(-2), Some(7))
+        );
+        assert_eq!(window.push(4), Some(-2));
+        assert_eq!(
+            (window.sum(), window.min(), window.max()),
+            (12, Some(1), Some(7))
+        );
+        assert_eq!(window.mean(), Some(4.0));
+        assert_eq!(window.iter().copied().collect::<Vec<_>>(), vec![7, 1, 4]);
+    }
+
+    #[test]
+    fn sliding_min_max_disagree_with_rescanning() {
+        let mut rng = Rng::new(29);
+        // Narrow enough that there are plenty of repeats
+        let values: Vec<u64> = (0..2000).map(|_| rng.below(50)).collect();
+
+        for (aggregate, chunk) in values.iter().copied().sliding::<5>().zip(values.windows(5)) {
+            assert_eq!(aggregate.sum, chunk.iter().sum::<u64>());
+            assert_eq!(aggregate.min, *chunk.iter().min().unwrap());
+            assert_eq!(aggregate.max, *chunk.iter().max().unwrap());
+        }
+        assert_eq!(values.iter().copied().sliding::<5>().count(), 1996);
+    }
+}
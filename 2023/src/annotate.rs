@@ -0,0 +1,341 @@
+//! Print a line with parts of it pointed at and labelled, for explaining an
+//! answer or showing where a parse went wrong.
+//!
+//! ```text
+//! 3 | xtwone3four
+//!   |  ^^^   ---- four
+//!   |  |
+//!   |  two
+//! ```
+
+use std::{
+	fmt,
+	io::{IsTerminal, Write},
+	ops::Range,
+};
+
+use crate::text::{pad_to, str_width};
+
+/// How a span is marked under the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+	/// `^^^`, for what we're pointing at
+	Caret,
+	/// `---`, for context around it
+	Underline,
+}
+
+impl Mark {
+	fn symbol(&self) -> char {
+		match self {
+			Mark::Caret => '^',
+			Mark::Underline => '-',
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+	Red,
+	Green,
+	Yellow,
+	Blue,
+	Magenta,
+	Cyan,
+}
+
+impl Colour {
+	fn code(&self) -> u8 {
+		match self {
+			Colour::Red => 31,
+			Colour::Green => 32,
+			Colour::Yellow => 33,
+			Colour::Blue => 34,
+			Colour::Magenta => 35,
+			Colour::Cyan => 36,
+		}
+	}
+}
+
+/// A part of the line, in bytes like everything else that points into a
+/// line. An empty span, like one at the very end to say something's
+/// missing, still gets one mark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+	pub range: Range<usize>,
+	pub label: Option<String>,
+	pub mark: Mark,
+	pub colour: Colour,
+}
+
+impl Span {
+	pub fn new(range: Range<usize>, mark: Mark, colour: Colour) -> Self {
+		Self {
+			range,
+			label: None,
+			mark,
+			colour,
+		}
+	}
+
+	pub fn label<S: Into<String>>(mut self, label: S) -> Self {
+		self.label = Some(label.into());
+		self
+	}
+}
+
+/// A line and the spans in it. Spans are drawn left to right and shouldn't
+/// overlap; when they do the later one loses the part that does.
+#[derive(Debug, Clone)]
+pub struct Annotated<'a> {
+	line: &'a str,
+	line_number: Option<usize>,
+	spans: Vec<Span>,
+}
+
+impl<'a> Annotated<'a> {
+	pub fn new(line: &'a str) -> Self {
+		Self {
+			line,
+			line_number: None,
+			spans: vec![],
+		}
+	}
+
+	/// Show the line number in a gutter down the left
+	pub fn line_number(mut self, line_number: usize) -> Self {
+		self.line_number = Some(line_number);
+		self
+	}
+
+	/// # Panics
+	/// If the span isn't on char boundaries or runs off the end of the line.
+	pub fn span(mut self, span: Span) -> Self {
+		assert!(
+			self.line.is_char_boundary(span.range.start)
+				&& self.line.is_char_boundary(span.range.end),
+			"span {:?} isn't on char boundaries",
+			span.range
+		);
+
+		self.spans.push(span);
+		self.spans.sort_by_key(|s| s.range.start);
+		self
+	}
+
+	/// Point at `range` with carets and a label.
+	pub fn caret<S: Into<String>>(self, range: Range<usize>, label: S, colour: Colour) -> Self {
+		self.span(Span::new(range, Mark::Caret, colour).label(label))
+	}
+
+	/// Underline `range` with a label.
+	pub fn underline<S: Into<String>>(self, range: Range<usize>, label: S, colour: Colour) -> Self {
+		self.span(Span::new(range, Mark::Underline, colour).label(label))
+	}
+
+	/// Everything, with ANSI colours if `colour`.
+	pub fn render(&self, colour: bool) -> String {
+		let paint = |text: &str, c: Colour| {
+			if colour {
+				format!("\x1b[1;{}m{text}\x1b[0m", c.code())
+			} else {
+				text.to_owned()
+			}
+		};
+
+		let gutter = self.line_number.map(|n| n.to_string().len());
+		let mut rows = vec![self.line.to_owned()];
+
+		// the marks, and the last span's label at the end of them
+		let mut marks = Row::default();
+		for span in &self.spans {
+			let end = span.range.end.max(self.first_char_end(span.range.start));
+			let width = str_width(self.line.get(span.range.start..end).unwrap_or("")).max(1);
+			let symbol = span.mark.symbol().to_string().repeat(width);
+			marks.push(
+				self.line,
+				span.range.start,
+				end,
+				&paint(&symbol, span.colour),
+				width,
+			);
+		}
+
+		let (last, rest) = match self.spans.split_last() {
+			Some((last, rest)) => (Some(last), rest),
+			None => (None, &[][..]),
+		};
+		if let Some(label) = last.and_then(|s| s.label.as_ref()) {
+			marks.text.push(' ');
+			marks.text.push_str(&paint(label, last.unwrap().colour));
+		}
+		if !self.spans.is_empty() {
+			rows.push(marks.text);
+		}
+
+		// every other label hangs off a | under the start of its span, the
+		// rightmost first so the lines don't cross
+		let labelled: Vec<&Span> = rest.iter().filter(|s| s.label.is_some()).collect();
+		for (idx, span) in labelled.iter().enumerate().rev() {
+			let mut pipes = Row::default();
+			let mut row = Row::default();
+
+			for pending in &labelled[..idx] {
+				let (start, end) = (
+					pending.range.start,
+					self.first_char_end(pending.range.start),
+				);
+				let pipe = paint("|", pending.colour);
+				pipes.push(self.line, start, end, &pipe, 1);
+				row.push(self.line, start, end, &pipe, 1);
+			}
+
+			let start = span.range.start;
+			let end = self.first_char_end(start);
+			pipes.push(self.line, start, end, &paint("|", span.colour), 1);
+			row.push_last(
+				self.line,
+				start,
+				&paint(span.label.as_ref().unwrap(), span.colour),
+			);
+
+			// only one row of pipes before the first label, like rustc
+			if idx == labelled.len() - 1 {
+				rows.push(pipes.text);
+			}
+			rows.push(row.text);
+		}
+
+		let mut out = String::new();
+		for (idx, row) in rows.iter().enumerate() {
+			match (gutter, idx) {
+				(Some(width), 0) => {
+					let number = self.line_number.unwrap().to_string();
+					out.push_str(&paint(&format!("{number:>width$} | "), Colour::Blue))
+				}
+				(Some(width), _) => {
+					out.push_str(&paint(&format!("{:>width$} | ", ""), Colour::Blue))
+				}
+				(None, _) => (),
+			}
+
+			match idx {
+				0 => out.push_str(row),
+				_ => out.push_str(row.trim_end()),
+			}
+			out.push('\n');
+		}
+
+		out
+	}
+
+	/// Print to stdout, coloured if it's a terminal and `NO_COLOR` isn't set.
+	pub fn print(&self) {
+		let colour = use_colour(std::io::stdout().is_terminal());
+		print!("{}", self.render(colour));
+	}
+
+	/// Print to stderr, coloured if it's a terminal and `NO_COLOR` isn't set.
+	pub fn eprint(&self) {
+		let colour = use_colour(std::io::stderr().is_terminal());
+		let _ = std::io::stderr().write_all(self.render(colour).as_bytes());
+	}
+
+	/// Where the char at `byte` ends, or `byte` if it's the end of the line.
+	fn first_char_end(&self, byte: usize) -> usize {
+		byte + self.line[byte..]
+			.chars()
+			.next()
+			.map(char::len_utf8)
+			.unwrap_or(0)
+	}
+}
+
+fn use_colour(terminal: bool) -> bool {
+	terminal && std::env::var_os("NO_COLOR").is_none()
+}
+
+impl fmt::Display for Annotated<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.render(false))
+	}
+}
+
+/// One row under the line, built left to right. `done` is the byte of the
+/// line we're lined up under.
+#[derive(Default)]
+struct Row {
+	text: String,
+	done: usize,
+}
+
+impl Row {
+	/// Put `text`, which is `width` columns wide, under `line[start..end]`.
+	/// Skipped if we're already past `start`.
+	fn push(&mut self, line: &str, start: usize, end: usize, text: &str, width: usize) {
+		if start < self.done {
+			return;
+		}
+
+		self.text
+			.push_str(&pad_to(&line[self.done..], start - self.done));
+		self.text.push_str(text);
+
+		// a | under a wide char needs to make up the difference
+		let covered = str_width(&line[start..end]);
+		self.text
+			.push_str(&" ".repeat(covered.saturating_sub(width)));
+		self.done = end;
+	}
+
+	/// Put `text` under `start` with nothing after it, so it can be as wide
+	/// as it likes.
+	fn push_last(&mut self, line: &str, start: usize, text: &str) {
+		if start >= self.done {
+			self.text
+				.push_str(&pad_to(&line[self.done..], start - self.done));
+		}
+		self.text.push_str(text);
+		self.done = line.len();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn annotation_is_misdrawn() {
+		let line = "a\tbb二cc dd";
+		let annotated = Annotated::new(line)
+			.line_number(12)
+			.span(Span::new(0..1, Mark::Caret, Colour::Red).label("first"))
+			.underline(2..7, "wide", Colour::Blue)
+			.span(Span::new(7..9, Mark::Underline, Colour::Green))
+			.caret(10..12, "last", Colour::Yellow);
+
+		// 二 is two columns so its underline is too, and the tab is kept
+		assert_eq!(
+			annotated.to_string(),
+			[
+				"12 | a\tbb二cc dd",
+				"   | ^\t------ ^^ last",
+				"   | |\t|",
+				"   | |\twide",
+				"   | first",
+				"",
+			]
+			.join("\n")
+		);
+
+		// colours only wrap the marks and labels
+		let coloured = Annotated::new("ab")
+			.caret(1..2, "b", Colour::Red)
+			.render(true);
+		assert_eq!(coloured, "ab\n \x1b[1;31m^\x1b[0m \x1b[1;31mb\x1b[0m\n");
+
+		// an empty span at the end still gets a mark
+		let end = Annotated::new("abc").caret(3..3, "more", Colour::Red);
+		assert_eq!(end.to_string(), "abc\n   ^ more\n");
+	}
+}
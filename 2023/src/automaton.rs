@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+
+/// Finds every match of a set of patterns in one pass over the text,
+/// overlapping ones too, so `eightwo` gives both `eight` and `two`. It's
+/// Aho-Corasick with the failure links folded into a full transition table,
+/// so every byte is one lookup.
+///
+/// Patterns are `str`s so matches always start and end on char boundaries.
+#[derive(Debug, Clone)]
+pub struct AhoCorasick<V> {
+	/// The state we go to from every state on every byte
+	next: Vec<[usize; 256]>,
+	/// The patterns that end in each state, longest first
+	outputs: Vec<Vec<usize>>,
+	/// Each pattern's length in bytes and what it means
+	patterns: Vec<(usize, V)>,
+}
+
+/// The root is always the first state
+const ROOT: usize = 0;
+/// Marks a transition the trie doesn't have while we're building
+const MISSING: usize = usize::MAX;
+
+impl<V> AhoCorasick<V> {
+	/// # Panics
+	/// If any pattern is empty. It'd match everywhere, which is never what
+	/// anybody meant.
+	pub fn new<P: AsRef<str>, I: IntoIterator<Item = (P, V)>>(patterns: I) -> Self {
+		Self::build(patterns, false)
+	}
+
+	/// Like [AhoCorasick::new] but `A` through `Z` match either case. Only
+	/// ASCII, so `Ü` and `ü` are still different.
+	///
+	/// # Panics
+	/// If any pattern is empty.
+	pub fn new_ascii_case_insensitive<P: AsRef<str>, I: IntoIterator<Item = (P, V)>>(
+		patterns: I,
+	) -> Self {
+		Self::build(patterns, true)
+	}
+
+	fn build<P: AsRef<str>, I: IntoIterator<Item = (P, V)>>(
+		patterns: I,
+		ignore_case: bool,
+	) -> Self {
+		let mut next = vec![[MISSING; 256]];
+		let mut outputs = vec![vec![]];
+		let mut values = vec![];
+
+		// Build the trie
+		for (idx, (pattern, value)) in patterns.into_iter().enumerate() {
+			let pattern = pattern.as_ref();
+			assert!(!pattern.is_empty(), "AhoCorasick given an empty pattern");
+
+			let mut state = ROOT;
+			for byte in pattern.bytes() {
+				let byte = if ignore_case {
+					byte.to_ascii_lowercase()
+				} else {
+					byte
+				};
+
+				if next[state][byte as usize] == MISSING {
+					next.push([MISSING; 256]);
+					outputs.push(vec![]);
+					next[state][byte as usize] = next.len() - 1;
+				}
+				state = next[state][byte as usize];
+			}
+
+			outputs[state].push(idx);
+			values.push((pattern.len(), value));
+		}
+
+		// Fill in the missing transitions breadth first, so a state's failure
+		// state is always finished before it is. Anything the root doesn't
+		// have goes back to the root.
+		let mut fail = vec![ROOT; next.len()];
+		let mut queue = VecDeque::new();
+		for slot in next[ROOT].iter_mut() {
+			match *slot {
+				MISSING => *slot = ROOT,
+				child => queue.push_back(child),
+			}
+		}
+
+		while let Some(state) = queue.pop_front() {
+			// A state matches everything its failure state does too. Those
+			// are all shorter, so longest first holds.
+			let inherited = outputs[fail[state]].clone();
+			outputs[state].extend(inherited);
+
+			let fallbacks = next[fail[state]];
+			for (slot, fallback) in next[state].iter_mut().zip(fallbacks) {
+				match *slot {
+					MISSING => *slot = fallback,
+					child => {
+						fail[child] = fallback;
+						queue.push_back(child);
+					}
+				}
+			}
+		}
+
+		// Only lowercase went into the trie, so uppercase just goes wherever
+		// lowercase does
+		if ignore_case {
+			for state in next.iter_mut() {
+				for upper in b'A'..=b'Z' {
+					state[upper as usize] = state[upper.to_ascii_lowercase() as usize];
+				}
+			}
+		}
+
+		Self {
+			next,
+			outputs,
+			patterns: values,
+		}
+	}
+
+	/// Every match in `haystack` in the order they end. Matches that end in
+	/// the same place come longest first.
+	pub fn find_overlapping<'a>(&'a self, haystack: &'a str) -> Matches<'a, V> {
+		Matches {
+			automaton: self,
+			haystack: haystack.as_bytes(),
+			pos: 0,
+			state: ROOT,
+			output: 0,
+		}
+	}
+
+	/// The match that starts earliest. The longest if there's a tie.
+	pub fn first<'a>(&'a self, haystack: &'a str) -> Option<Match<'a, V>> {
+		self.find_overlapping(haystack).reduce(|best, m| {
+			if m.start < best.start || (m.start == best.start && m.end > best.end) {
+				m
+			} else {
+				best
+			}
+		})
+	}
+
+	/// The match that ends latest. The longest if there's a tie, so like
+	/// [AhoCorasick::first] it's never inside a longer match: the last in
+	/// `viii` is `viii` and not `i`. When no pattern is inside another that's
+	/// also the one that starts latest.
+	pub fn last<'a>(&'a self, haystack: &'a str) -> Option<Match<'a, V>> {
+		self.find_overlapping(haystack)
+			// matches that end in the same place come longest first, so keep
+			// the first of them
+			.reduce(|best, m| if m.end > best.end { m } else { best })
+	}
+}
+
+/// Where a pattern was found, in bytes, and the value it was given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match<'a, V> {
+	pub start: usize,
+	pub end: usize,
+	pub value: &'a V,
+}
+
+/// Made by [AhoCorasick::find_overlapping].
+pub struct Matches<'a, V> {
+	automaton: &'a AhoCorasick<V>,
+	haystack: &'a [u8],
+	/// How much of the haystack we've read
+	pos: usize,
+	state: usize,
+	/// How many of the current state's outputs we've given out
+	output: usize,
+}
+
+impl<'a, V> Iterator for Matches<'a, V> {
+	type Item = Match<'a, V>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(pattern) = self.automaton.outputs[self.state].get(self.output) {
+				self.output += 1;
+
+				let (len, value) = &self.automaton.patterns[*pattern];
+				return Some(Match {
+					start: self.pos - len,
+					end: self.pos,
+					value,
+				});
+			}
+
+			let byte = *self.haystack.get(self.pos)?;
+			self.state = self.automaton.next[self.state][byte as usize];
+			self.pos += 1;
+			self.output = 0;
+		}
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+	use crate::vocabulary::Vocabulary;
+
+	use super::*;
+
+	/// Where every match starts and its value, for the other modules' tests too
+	pub(crate) fn found(matcher: &AhoCorasick<usize>, line: &str) -> Vec<(usize, usize)> {
+		matcher
+			.find_overlapping(line)
+			.map(|m| (m.start, *m.value))
+			.collect()
+	}
+
+	#[test]
+	fn nested_patterns_are_missed() {
+		// she contains he, hers contains he, and they all overlap
+		let matcher = AhoCorasick::new([("he", 0), ("she", 1), ("his", 2), ("hers", 3)]);
+		let found: Vec<_> = matcher
+			.find_overlapping("ushers")
+			.map(|m| (m.start, m.end, *m.value))
+			.collect();
+
+		assert_eq!(found, vec![(1, 4, 1), (2, 4, 0), (2, 6, 3)]);
+	}
+
+	#[test]
+	fn tied_matches_pick_the_shortest() {
+		// both start at 0, so the longer one wins whichever end we look from
+		let matcher = AhoCorasick::new([("one", 1), ("oneight", 18)]);
+		let first = matcher.first("oneight").unwrap();
+		let last = matcher.last("oneight").unwrap();
+
+		assert_eq!((first.start, first.end, *first.value), (0, 7, 18));
+		assert_eq!((last.start, last.end, *last.value), (0, 7, 18));
+
+		// and it doesn't matter which order they were given in
+		let matcher = AhoCorasick::new([("oneight", 18), ("one", 1)]);
+		assert_eq!(*matcher.first("xoneight").unwrap().value, 18);
+		assert_eq!(*matcher.last("xoneight").unwrap().value, 18);
+	}
+
+	#[test]
+	fn utf8_matches_are_off_boundary() {
+		let english = Vocabulary::english();
+		let digits = english.matcher();
+
+		// ñ and é are two bytes, 二 is three, 🎄 is four
+		let line = "ñoñeight二🎄twoé9";
+		for m in digits.find_overlapping(line) {
+			assert!(line.is_char_boundary(m.start) && line.is_char_boundary(m.end));
+		}
+
+		let first = digits.first(line).unwrap();
+		let last = digits.last(line).unwrap();
+		assert_eq!(&line[first.start..first.end], "eight");
+		assert_eq!(&line[last.start..last.end], "9");
+		assert_eq!(found(digits, line), vec![(5, 8), (17, 2), (22, 9)]);
+	}
+}
@@ -1,137 +1,117 @@
 use std::str::FromStr;
 
-use aoc2023::{ParseFile, RuntimeError};
+use aoc2023::{
+	annotate::{Annotated, Colour},
+	error,
+	log::{self, Level},
+	vocabulary::Vocabulary,
+	ParseFile, RuntimeError,
+};
+
+fn main() {
+	if let Err(e) = run() {
+		if let Some(diagnostic) = e.diagnostic() {
+			diagnostic.eprint();
+		}
 
-fn main() -> Result<(), RuntimeError> {
-	let cal = Calibration::parse_file("input/day1")?;
+		error!("{e}");
+		std::process::exit(1);
+	}
+}
 
-	let mut sum = 0;
-	for (ln, line) in cal.lines.iter().enumerate() {
-		// The digits (0-9) in the line with their positions
-		let mut digits: Vec<(usize, u32)> = line
-			.chars()
-			.enumerate()
-			.filter_map(|(idx, c)| c.to_digit(10).map(|d| (idx, d)))
-			.collect();
-
-		let first_digit = digits.first();
-		let first_word_digit = find_word_digit(line);
-
-		// these might be the same as the firsts's but that's fine
-		let last_digit = digits.last();
-		let last_word_digit = rfind_word_digit(line);
-
-		let (tens_idx, tens) = match (first_digit, first_word_digit) {
-			(Some((digit_idx, digit_num)), Some((word_idx, word_num))) => {
-				if *digit_idx > word_idx {
-					(word_idx, word_num)
-				} else {
-					(*digit_idx, *digit_num as usize)
-				}
-			}
-			(Some((digit_idx, digit_num)), None) => (*digit_idx, *digit_num as usize),
-			(None, Some(word)) => word,
-			(None, None) => {
-				eprintln!("{ln}: line has no numbers of any form");
-				std::process::exit(1);
+fn run() -> Result<(), RuntimeError> {
+	let paths = vocabulary_paths(std::env::args().skip(1))?;
+	let cal = Calibration::parse_file("input/day1")?;
+	let english = Vocabulary::english();
+
+	// the explanations are a lot, so only with -v. lines without any
+	// digits are an error from cal.part2 below
+	if log::enabled(Level::Info, log::target_of(file!())) {
+		for line in &cal.lines {
+			if let Some(explained) = explain(&english, line) {
+				explained.eprint();
 			}
-		};
-
-		let (ones_idx, ones) =
-			match (last_digit, last_word_digit) {
-				(Some((digit_idx, digit_num)), Some((word_idx, word_num))) => {
-					println!("{ln}: [{digit_idx},{digit_num}] [{word_idx},{word_num}]");
-
-					if *digit_idx < word_idx {
-						(word_idx, word_num)
-					} else {
-						(*digit_idx, *digit_num as usize)
-					}
-				}
-				(Some((digit_idx, digit_num)), None) => (*digit_idx, *digit_num as usize),
-				(None, Some(word)) => word,
-				(None, None) => {
-					eprintln!("{ln}: line has no numbers of any form. we're at last, how did we get here?");
-					std::process::exit(1);
-				}
-			};
+		}
+	}
 
-		sum += tens * 10 + ones;
+	println!("part 1 = {}", cal.part1()?);
+	println!("part 2 = {}", cal.part2()?);
 
-		println!("{line}");
-		println!(
-			"{:>firstpad$}^{:>secondpad$}^",
-			"",
-			"",
-			firstpad = tens_idx,
-			secondpad = (ones_idx - tens_idx).saturating_sub(1)
-		);
+	// any vocabulary files we were given, to read the lines with too
+	for path in paths {
+		let vocabulary = Vocabulary::parse_file(path.as_str())?;
+		println!("{path} = {}", cal.sum(&vocabulary)?);
 	}
 
-	println!("sum = {sum}");
-
 	Ok(())
 }
 
-const NUMBERS: [&'static str; 10] = [
-	"zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
-];
-
-fn find_word_digit(line: &str) -> Option<(usize, usize)> {
-	let mut lowest_idx = None;
-	let mut lowest_digit = None;
-
-	for (digit, number) in NUMBERS.iter().enumerate() {
-		match (line.find(number), lowest_idx) {
-			(Some(found_idx), Some(low_idx)) if found_idx < low_idx => {
-				lowest_idx = Some(found_idx);
-				lowest_digit = Some(digit);
-			}
-			(Some(found_idx), None) => {
-				lowest_idx = Some(found_idx);
-				lowest_digit = Some(digit);
-			}
-			_ => (),
+/// The vocabulary files in `args`. The flags are for log, and anything else
+/// starting with `-` is a mistake.
+fn vocabulary_paths<I: IntoIterator<Item = String>>(args: I) -> Result<Vec<String>, RuntimeError> {
+	let mut paths = vec![];
+	for arg in args.into_iter().filter(|arg| !log::is_flag(arg)) {
+		if arg.starts_with('-') {
+			return Err(RuntimeError::UnknownFlag { flag: arg });
 		}
+		paths.push(arg);
 	}
 
-	// we never set _idx without _digits, but it's still
-	// not good to unwrap here. we should be using one varaible
-	// as Option<(usize, usize)> but i want to get to day2 and this
-	// is advent of code not boeing 747 max *(i don't need to care)*
-	lowest_idx.map(|idx| (idx, lowest_digit.unwrap()))
+	Ok(paths)
 }
 
-// copy pasted 'cause i'm wasted (i'm not drunk i just liked the rhyme)
-fn rfind_word_digit(line: &str) -> Option<(usize, usize)> {
-	let mut highest_idx = None;
-	let mut highest_digit = None;
+/// The line with the first and last digit or word pointed out. They might
+/// be the same one, then it's only pointed at once.
+fn explain<'a>(vocabulary: &'a Vocabulary, line: &'a str) -> Option<Annotated<'a>> {
+	let (first, last) = vocabulary.first_last(line)?;
 
-	for (digit, number) in NUMBERS.iter().enumerate() {
-		match (line.rfind(number), highest_idx) {
-			(Some(found_idx), Some(high_idx)) if found_idx > high_idx => {
-				highest_idx = Some(found_idx);
-				highest_digit = Some(digit);
-			}
-			(Some(found_idx), None) => {
-				highest_idx = Some(found_idx);
-				highest_digit = Some(digit);
-			}
-			_ => (),
-		}
+	let annotated = Annotated::new(line);
+	if first == last {
+		let label = format!("{0}{0}", first.value);
+		return Some(annotated.caret(first.start..first.end, label, Colour::Green));
 	}
 
-	// we never set _idx without _digits, but it's still
-	// not good to unwrap here. we should be using one varaible
-	// as Option<(usize, usize)> but i want to get to day2 and this
-	// is advent of code not boeing 747 max *(i don't need to care)*
-	highest_idx.map(|idx| (idx, highest_digit.unwrap()))
+	Some(
+		annotated
+			.caret(
+				first.start..first.end,
+				first.value.to_string(),
+				Colour::Green,
+			)
+			.caret(last.start..last.end, last.value.to_string(), Colour::Cyan),
+	)
 }
 
 pub struct Calibration {
 	pub lines: Vec<String>,
 }
 
+impl Calibration {
+	pub fn part1(&self) -> Result<usize, RuntimeError> {
+		self.sum(&Vocabulary::digits())
+	}
+
+	pub fn part2(&self) -> Result<usize, RuntimeError> {
+		self.sum(&Vocabulary::english())
+	}
+
+	/// Every line's value added up. Every line has to have a digit in it.
+	pub fn sum(&self, vocabulary: &Vocabulary) -> Result<usize, RuntimeError> {
+		self.lines
+			.iter()
+			.enumerate()
+			.map(|(ln, line)| {
+				vocabulary
+					.value(line)
+					.ok_or_else(|| RuntimeError::NoCalibrationValue {
+						line: ln + 1,
+						text: line.to_owned(),
+					})
+			})
+			.sum()
+	}
+}
+
 impl FromStr for Calibration {
 	type Err = RuntimeError;
 
@@ -141,3 +121,109 @@ impl FromStr for Calibration {
 		})
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use aoc2023::vocabulary::Language;
+
+	use super::*;
+
+	#[test]
+	fn matcher_disagrees_with_find() {
+		let english = Vocabulary::english();
+		let digits = english.matcher();
+		let cal = Calibration::parse_file("input/day1").unwrap();
+
+		for line in &cal.lines {
+			// what the word and digit searching used to find
+			let mut expected: Vec<(usize, usize)> = vec![];
+			for (digit, word) in Language::English.words().iter().enumerate() {
+				let number = digit.to_string();
+				for pattern in [word, number.as_str()] {
+					expected.extend(line.match_indices(pattern).map(|(idx, _)| (idx, digit)));
+				}
+			}
+			expected.sort();
+
+			let mut found: Vec<(usize, usize)> = digits
+				.find_overlapping(line)
+				.map(|m| (m.start, *m.value))
+				.collect();
+			found.sort();
+			assert_eq!(found, expected, "{line}");
+
+			let first = digits.first(line).unwrap();
+			let last = digits.last(line).unwrap();
+			assert_eq!((first.start, *first.value), expected[0]);
+			assert_eq!((last.start, *last.value), *expected.last().unwrap());
+		}
+	}
+
+	#[test]
+	fn part1_example_is_wrong() {
+		let cal: Calibration = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet"
+			.parse()
+			.unwrap();
+		assert_eq!(cal.part1().unwrap(), 142);
+	}
+
+	#[test]
+	fn part2_example_is_wrong() {
+		let cal = Calibration::parse_file("input/day1_test").unwrap();
+		assert_eq!(cal.part2().unwrap(), 281);
+
+		// some of these lines have no digits, only words
+		assert!(matches!(
+			cal.part1(),
+			Err(RuntimeError::NoCalibrationValue { line: 2, .. })
+		));
+	}
+
+	#[test]
+	fn real_input_is_wrong() {
+		let cal = Calibration::parse_file("input/day1").unwrap();
+		assert_eq!(cal.part1().unwrap(), 56042);
+		assert_eq!(cal.part2().unwrap(), 55358);
+	}
+
+	#[test]
+	fn explanation_is_wrong() {
+		let english = Vocabulary::english();
+
+		let explained = explain(&english, "xtwone3four").unwrap();
+		assert_eq!(
+			explained.to_string(),
+			"xtwone3four\n ^^^   ^^^^ 4\n |\n 2\n"
+		);
+
+		let explained = explain(&english, "treb7uchet").unwrap();
+		assert_eq!(explained.to_string(), "treb7uchet\n    ^ 77\n");
+
+		assert!(explain(&english, "nothing").is_none());
+	}
+
+	#[test]
+	fn unknown_flags_are_ignored() {
+		let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+		let paths = vocabulary_paths(args(&["-vv", "roman.txt", "-q", "./-vocab.txt"])).unwrap();
+		assert_eq!(paths, ["roman.txt", "./-vocab.txt"]);
+
+		for bad in ["-vocab.txt", "--exact", "-x", "-"] {
+			assert!(matches!(
+				vocabulary_paths(args(&["a.txt", bad])),
+				Err(RuntimeError::UnknownFlag { flag }) if flag == bad
+			));
+		}
+	}
+
+	#[test]
+	fn calibration_diagnostic_points_at_the_wrong_place() {
+		let cal: Calibration = "1\nabc".parse().unwrap();
+		let err = cal.part1().unwrap_err();
+		assert_eq!(
+			err.diagnostic().unwrap().to_string(),
+			"2 | abc\n  | --- no digits in here\n"
+		);
+	}
+}
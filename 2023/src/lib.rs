@@ -1,7 +1,14 @@
-use std::str::FromStr;
+use std::{ops::Range, str::FromStr};
 
+use annotate::{Annotated, Colour};
 use camino::Utf8PathBuf;
 
+pub mod annotate;
+pub mod automaton;
+pub mod log;
+pub mod text;
+pub mod vocabulary;
+
 pub trait ParseFile: FromStr
 where
 	<Self as FromStr>::Err: Into<RuntimeError>,
@@ -32,4 +39,43 @@ pub enum RuntimeError {
 		path: Utf8PathBuf,
 		io: std::io::Error,
 	},
+	#[error("line {line} has no calibration value, there aren't any digits in it")]
+	NoCalibrationValue { line: usize, text: String },
+	#[error("line {line} of the vocabulary is wrong, {reason}")]
+	BadVocabulary {
+		line: usize,
+		reason: String,
+		text: String,
+		/// The part of `text` that's wrong, in bytes
+		span: Range<usize>,
+	},
+	#[error("'{flag}' isn't a flag, only -v and -q are. Write ./{flag} for a file")]
+	UnknownFlag { flag: String },
+}
+
+impl RuntimeError {
+	/// The line that went wrong with what's wrong with it pointed out, if
+	/// the error came from a line.
+	pub fn diagnostic(&self) -> Option<Annotated<'_>> {
+		match self {
+			RuntimeError::FileIoError { .. } | RuntimeError::UnknownFlag { .. } => None,
+			RuntimeError::NoCalibrationValue { line, text } => {
+				Some(Annotated::new(text).line_number(*line).underline(
+					0..text.len(),
+					"no digits in here",
+					Colour::Red,
+				))
+			}
+			RuntimeError::BadVocabulary {
+				line,
+				reason,
+				text,
+				span,
+			} => Some(Annotated::new(text).line_number(*line).caret(
+				span.clone(),
+				reason,
+				Colour::Red,
+			)),
+		}
+	}
 }
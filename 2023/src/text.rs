@@ -0,0 +1,112 @@
+//! Positions in a line are always byte offsets, which is what `str` slicing
+//! and [crate::automaton] use. This is for getting from those to where
+//! things end up on a terminal.
+
+/// How many columns a char takes up on a terminal. Tabs are their own thing,
+/// see [pad_to]. This is a rough cut of the East Asian Width tables that
+/// gets CJK, Hangul, fullwidth forms and the common emoji right, and treats
+/// combining marks and the zero width characters as taking no room at all.
+pub fn char_width(c: char) -> usize {
+	match c as u32 {
+		// combining marks, zero width space/joiners, variation selectors
+		0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => 0,
+		0x0000..=0x001F | 0x007F..=0x009F => 0,
+		0x1100..=0x115F
+		| 0x2E80..=0x303E
+		| 0x3041..=0x33FF
+		| 0x3400..=0x4DBF
+		| 0x4E00..=0x9FFF
+		| 0xA000..=0xA4CF
+		| 0xAC00..=0xD7A3
+		| 0xF900..=0xFAFF
+		| 0xFE30..=0xFE4F
+		| 0xFF00..=0xFF60
+		| 0xFFE0..=0xFFE6
+		| 0x1F300..=0x1F64F
+		| 0x1F900..=0x1F9FF
+		| 0x20000..=0x2FFFD
+		| 0x30000..=0x3FFFD => 2,
+		_ => 1,
+	}
+}
+
+/// How many columns `s` takes up, not counting tabs.
+pub fn str_width(s: &str) -> usize {
+	s.chars().map(char_width).sum()
+}
+
+/// Whitespace that puts the cursor under byte `byte` of `line` when printed
+/// on the line after it. Tabs are copied across rather than guessed at so
+/// they line up however wide the terminal thinks they are.
+///
+/// # Panics
+/// If `byte` isn't on a char boundary.
+pub fn pad_to(line: &str, byte: usize) -> String {
+	let mut pad = String::new();
+
+	for c in line[..byte].chars() {
+		match c {
+			'\t' => pad.push('\t'),
+			c => pad.push_str(&" ".repeat(char_width(c))),
+		}
+	}
+
+	pad
+}
+
+/// A caret under the char at each of `bytes` in `line`, as wide as the char
+/// is. They have to be in order; ones that land on a char already marked
+/// are skipped.
+pub fn carets(line: &str, bytes: &[usize]) -> String {
+	let mut marker = String::new();
+	let mut done = 0;
+
+	for byte in bytes.iter().copied().filter(|b| *b < line.len()) {
+		if byte < done {
+			continue;
+		}
+
+		marker.push_str(&pad_to(&line[done..], byte - done));
+		let c = line[byte..].chars().next().unwrap();
+		marker.push_str(&"^".repeat(char_width(c).max(1)));
+		done = byte + c.len_utf8();
+	}
+
+	marker
+}
+
+#[cfg(test)]
+mod test {
+	use crate::vocabulary::Vocabulary;
+
+	use super::*;
+
+	#[test]
+	fn carets_are_misaligned() {
+		assert_eq!(carets("1abc2", &[0, 4]), "^   ^");
+		// the same one twice is only marked once
+		assert_eq!(carets("ab3", &[2, 2]), "  ^");
+		// two bytes but one column
+		assert_eq!(carets("é1x2", &[2, 4]), " ^ ^");
+		// tabs stay tabs so they go as far as the line above did
+		assert_eq!(carets("\tone\t2", &[1, 5]), "\t^  \t^");
+		// wide chars take two columns, and get two carets under them
+		assert_eq!(carets("二1🎄2", &[3, 8]), "  ^  ^");
+		assert_eq!(carets("1二", &[0, 1]), "^^^");
+		// combining marks don't take any room
+		assert_eq!(carets("e\u{301}5", &[3]), " ^");
+	}
+
+	#[test]
+	fn utf8_carets_are_misaligned() {
+		let english = Vocabulary::english();
+		let digits = english.matcher();
+		let line = "ñ\t二sixé🎄7";
+		let first = digits.first(line).unwrap();
+		let last = digits.last(line).unwrap();
+
+		assert_eq!((*first.value, *last.value), (6, 7));
+		assert_eq!(carets(line, &[first.start, last.start]), " \t  ^     ^");
+		assert_eq!(str_width(line), 10);
+	}
+}
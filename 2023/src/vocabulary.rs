@@ -0,0 +1,387 @@
+use std::{ops::Range, str::FromStr};
+
+use crate::{
+	automaton::{AhoCorasick, Match},
+	RuntimeError,
+};
+
+/// The words and symbols we read as numbers in a line, like the `one` and
+/// `1` of 2023 day 1. Build one out of the ones here, or read one from a
+/// file of `token value` lines.
+#[derive(Debug, Clone)]
+pub struct Vocabulary {
+	tokens: Vec<(String, usize)>,
+	ignore_case: bool,
+	matcher: AhoCorasick<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+	English,
+	German,
+	French,
+	Spanish,
+}
+
+impl Language {
+	/// The words for zero through nine
+	pub fn words(&self) -> [&'static str; 10] {
+		match self {
+			Language::English => [
+				"zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+			],
+			Language::German => [
+				"null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+			],
+			Language::French => [
+				"zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+			],
+			Language::Spanish => [
+				"cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+			],
+		}
+	}
+}
+
+impl FromStr for Language {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"english" | "en" => Ok(Language::English),
+			"german" | "de" => Ok(Language::German),
+			"french" | "fr" => Ok(Language::French),
+			"spanish" | "es" => Ok(Language::Spanish),
+			_ => Err(format!("'{s}' isn't a language we know numbers in")),
+		}
+	}
+}
+
+const TEENS: [&str; 11] = [
+	"ten",
+	"eleven",
+	"twelve",
+	"thirteen",
+	"fourteen",
+	"fifteen",
+	"sixteen",
+	"seventeen",
+	"eighteen",
+	"nineteen",
+	"twenty",
+];
+
+const ROMAN: [&str; 20] = [
+	"I", "II", "III", "IV", "V", "VI", "VII", "VIII", "IX", "X", "XI", "XII", "XIII", "XIV", "XV",
+	"XVI", "XVII", "XVIII", "XIX", "XX",
+];
+
+impl Vocabulary {
+	/// # Panics
+	/// If any token is empty.
+	pub fn new<S: Into<String>, I: IntoIterator<Item = (S, usize)>>(tokens: I) -> Self {
+		let tokens: Vec<(String, usize)> = tokens.into_iter().map(|(s, v)| (s.into(), v)).collect();
+		Self::build(tokens, false)
+	}
+
+	fn build(tokens: Vec<(String, usize)>, ignore_case: bool) -> Self {
+		let pairs = tokens.iter().map(|(token, value)| (token, *value));
+		let matcher = if ignore_case {
+			AhoCorasick::new_ascii_case_insensitive(pairs)
+		} else {
+			AhoCorasick::new(pairs)
+		};
+
+		Self {
+			tokens,
+			ignore_case,
+			matcher,
+		}
+	}
+
+	/// Just 0 through 9, like part 1
+	pub fn digits() -> Self {
+		Self::new((0..10).map(|digit| (digit.to_string(), digit)))
+	}
+
+	/// The words for zero through nine, without the digits
+	pub fn words(language: Language) -> Self {
+		Self::new(language.words().into_iter().zip(0..))
+	}
+
+	/// The digits and the English words for them, like part 2
+	pub fn english() -> Self {
+		Self::digits().with(Self::words(Language::English))
+	}
+
+	/// `ten` through `twenty`
+	pub fn teens() -> Self {
+		Self::new(TEENS.into_iter().zip(10..))
+	}
+
+	/// `I` through `XX`. Uppercase, see [Vocabulary::ignoring_case].
+	pub fn roman() -> Self {
+		Self::new(ROMAN.into_iter().zip(1..))
+	}
+
+	/// Everything in here and everything in `other`. If we're ignoring case
+	/// so is what we get back.
+	pub fn with(self, other: Vocabulary) -> Self {
+		let mut tokens = self.tokens;
+		tokens.extend(other.tokens);
+		Self::build(tokens, self.ignore_case || other.ignore_case)
+	}
+
+	/// Adds every token spelled backwards too, so `owt` is 2. Palindromes
+	/// and single characters are only in once.
+	pub fn reversed(self) -> Self {
+		let backwards: Vec<(String, usize)> = self
+			.tokens
+			.iter()
+			.map(|(token, value)| (token.chars().rev().collect::<String>(), *value))
+			.filter(|(reversed, _)| !self.tokens.iter().any(|(token, _)| token == reversed))
+			.collect();
+
+		let mut tokens = self.tokens;
+		tokens.extend(backwards);
+		Self::build(tokens, self.ignore_case)
+	}
+
+	/// Match `One`, `ONE` and `oNe` as well as `one`. ASCII letters only.
+	pub fn ignoring_case(self) -> Self {
+		Self::build(self.tokens, true)
+	}
+
+	pub fn tokens(&self) -> &[(String, usize)] {
+		&self.tokens
+	}
+
+	pub fn matcher(&self) -> &AhoCorasick<usize> {
+		&self.matcher
+	}
+
+	/// The first and last tokens in `line`, see [AhoCorasick::first] and
+	/// [AhoCorasick::last]. Neither is ever part of a longer token, so the
+	/// last in `viii` is `viii` and not `i`, but the last in `eightwo` is
+	/// `two`.
+	pub fn first_last<'a>(&'a self, line: &'a str) -> Option<(Match<'a, usize>, Match<'a, usize>)> {
+		Some((self.matcher.first(line)?, self.matcher.last(line)?))
+	}
+
+	/// The number made of the first digit of the first token and the last
+	/// digit of the last one, as if every token was written out as digits.
+	/// That's just the two values for the digits, but `twelve` alone is 12.
+	pub fn value(&self, line: &str) -> Option<usize> {
+		let (first, last) = self.first_last(line)?;
+
+		let mut tens = *first.value;
+		while tens >= 10 {
+			tens /= 10;
+		}
+
+		Some(tens * 10 + last.value % 10)
+	}
+}
+
+/// A token and its value on each line, split by whitespace. Blank lines and
+/// ones starting `#` are skipped.
+impl FromStr for Vocabulary {
+	type Err = RuntimeError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut tokens = vec![];
+
+		for (ln, text) in s.lines().enumerate() {
+			let line = text.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+
+			let bad = |reason: &str, span: Range<usize>| RuntimeError::BadVocabulary {
+				line: ln + 1,
+				reason: reason.to_owned(),
+				text: text.to_owned(),
+				span,
+			};
+			// where a piece of the trimmed line is in the one we were given
+			let span_of = |part: &str| {
+				let start = part.as_ptr() as usize - text.as_ptr() as usize;
+				start..start + part.len()
+			};
+
+			let mut parts = line.split_whitespace();
+			let (token, value) = match (parts.next(), parts.next(), parts.next()) {
+				(Some(token), Some(value), None) => (token, value),
+				(Some(token), None, _) => {
+					let end = span_of(token).end;
+					return Err(bad("expected a value after the token", end..end));
+				}
+				(_, _, Some(extra)) => {
+					return Err(bad("expected only a token and a value", span_of(extra)))
+				}
+				(None, ..) => unreachable!("blank lines are skipped"),
+			};
+			let value = value
+				.parse()
+				.map_err(|_| bad("the value isn't a whole number", span_of(value)))?;
+
+			tokens.push((token.to_owned(), value));
+		}
+
+		Ok(Self::new(tokens))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::automaton::test::found;
+
+	use super::*;
+
+	#[test]
+	fn overlapping_words_are_missed() {
+		let english = Vocabulary::english();
+		let digits = english.matcher();
+
+		assert_eq!(found(digits, "eightwo"), vec![(0, 8), (4, 2)]);
+		assert_eq!(found(digits, "oneight"), vec![(0, 1), (2, 8)]);
+		assert_eq!(found(digits, "twone3"), vec![(0, 2), (2, 1), (5, 3)]);
+		assert_eq!(found(digits, "sevenine"), vec![(0, 7), (4, 9)]);
+		assert_eq!(found(digits, "abc"), vec![]);
+	}
+
+	#[test]
+	fn languages_are_misread() {
+		let german = Vocabulary::digits().with(Vocabulary::words(Language::German));
+		assert_eq!(german.value("xfünfzweiundsechzig"), Some(52));
+		assert_eq!(german.value("neunzehn3"), Some(93));
+
+		let french = Vocabulary::words(Language::French);
+		assert_eq!(french.value("zérotroisx"), Some(3));
+		assert_eq!(french.value("quatre-vingt-dix-neuf"), Some(49));
+
+		let spanish = Vocabulary::words(Language::Spanish);
+		assert_eq!(spanish.value("ochocientos"), Some(88));
+		assert!("klingon".parse::<Language>().is_err());
+	}
+
+	#[test]
+	fn mixed_case_is_misread() {
+		let english = Vocabulary::english();
+		assert_eq!(english.value("ONE2Three"), Some(22));
+
+		let english = english.ignoring_case();
+		assert_eq!(english.value("ONE2Three"), Some(13));
+		assert_eq!(english.value("xSeVeNiNe"), Some(79));
+	}
+
+	#[test]
+	fn reversed_is_misread() {
+		let both = Vocabulary::english().reversed();
+		assert_eq!(both.value("owtxenin"), Some(29));
+		assert_eq!(both.value("eno5thgie"), Some(18));
+		// single digits aren't in twice
+		assert_eq!(both.tokens().len(), 10 + 10 + 10);
+	}
+
+	#[test]
+	fn teens_are_misread() {
+		let teens = Vocabulary::english().with(Vocabulary::teens());
+		// eighteen has eight in it, but eighteen is the longer
+		assert_eq!(teens.value("eighteen"), Some(18));
+		assert_eq!(teens.value("twelve"), Some(12));
+		assert_eq!(teens.value("3xtwenty"), Some(30));
+		assert_eq!(teens.value("sixteen7"), Some(17));
+	}
+
+	#[test]
+	fn roman_numerals_are_misread() {
+		let roman = Vocabulary::roman().ignoring_case();
+		assert_eq!(roman.value("VIII"), Some(88));
+		assert_eq!(roman.value("ix and iv"), Some(94));
+		assert_eq!(roman.value("XIV"), Some(14));
+		assert_eq!(roman.value("xx"), Some(20));
+		assert_eq!(roman.value("abc"), None);
+	}
+
+	#[test]
+	fn first_last_disagrees_with_filtering_nested() {
+		let vocabulary = Vocabulary::english()
+			.with(Vocabulary::teens())
+			.with(Vocabulary::roman())
+			.ignoring_case()
+			.reversed();
+		let letters: Vec<char> = "eightwonevixlnt1".chars().collect();
+		let mut seed = 44u64;
+
+		for _ in 0..2000 {
+			let line: String = (0..12)
+				.map(|_| {
+					// xorshift, plenty for picking letters
+					seed ^= seed << 13;
+					seed ^= seed >> 7;
+					seed ^= seed << 17;
+					letters[(seed % letters.len() as u64) as usize]
+				})
+				.collect();
+
+			// the earliest and latest of the matches not inside a longer one
+			let matches: Vec<_> = vocabulary.matcher().find_overlapping(&line).collect();
+			let outermost: Vec<_> = matches
+				.iter()
+				.filter(|m| {
+					!matches.iter().any(|o| {
+						o.start <= m.start && m.end <= o.end && o.end - o.start > m.end - m.start
+					})
+				})
+				.map(|m| (m.start, m.end))
+				.collect();
+			let expected = outermost.iter().min().zip(outermost.iter().max());
+
+			let found = vocabulary
+				.first_last(&line)
+				.map(|(first, last)| ((first.start, first.end), (last.start, last.end)));
+			assert_eq!(found.as_ref().map(|(f, l)| (f, l)), expected, "{line}");
+		}
+	}
+
+	#[test]
+	fn vocabulary_file_is_misread() {
+		let vocabulary: Vocabulary = "# the first few\n\none 1\n  ichi 1\nni\t2\n\nsan 3\n"
+			.parse()
+			.unwrap();
+		assert_eq!(vocabulary.tokens().len(), 4);
+		assert_eq!(vocabulary.value("sanxoneni"), Some(32));
+
+		assert!(matches!(
+			"one 1\ntwo".parse::<Vocabulary>(),
+			Err(RuntimeError::BadVocabulary { line: 2, .. })
+		));
+		assert!(matches!(
+			"one one".parse::<Vocabulary>(),
+			Err(RuntimeError::BadVocabulary { line: 1, .. })
+		));
+	}
+
+	#[test]
+	fn diagnostics_point_at_the_wrong_place() {
+		let err = "one 1\n two   x\n".parse::<Vocabulary>().unwrap_err();
+		assert_eq!(
+			err.diagnostic().unwrap().to_string(),
+			"2 |  two   x\n  |        ^ the value isn't a whole number\n"
+		);
+
+		let err = "one 1\nsix".parse::<Vocabulary>().unwrap_err();
+		assert_eq!(
+			err.diagnostic().unwrap().to_string(),
+			"2 | six\n  |    ^ expected a value after the token\n"
+		);
+
+		let err = "one 1 2".parse::<Vocabulary>().unwrap_err();
+		assert!(err
+			.diagnostic()
+			.unwrap()
+			.to_string()
+			.ends_with("      ^ expected only a token and a value\n"));
+	}
+}